@@ -7,6 +7,8 @@ pub fn next_enum_variant(input: TokenStream) -> TokenStream {
 	let DeriveInput {data,ident,..} = parse_macro_input!(input);
 	match data {
 		Data::Enum(DataEnum{variants,..}) => {
+			// Just the names, without attributes like #[default].
+			let variants: Vec<_> = variants.iter().map(|variant|&variant.ident).collect();
 			let next_iter = variants.iter();
 			let next_shifted_iter = variants.iter().skip(1).chain(variants.iter().take(1));
 			
@@ -1,36 +1,7 @@
 use std::time::Duration;
 
 use crate::{vec2i,vec2f};
-use serde::{Serialize,Deserialize};
-
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
-pub struct Data {
-	texture_pos: vec2i,
-}
-impl Data {
-	pub const EMPTY: Data = Data{texture_pos:vec2i{x:0,y:0}};
-	pub const SHADOW: Data = Data{texture_pos:vec2i{x:1,y:0}};
-	pub const GRAY: Data = Data{texture_pos:vec2i{x:3,y:0}};
-	
-	pub const CYAN: Data = Data{texture_pos:vec2i{x:2,y:0}};
-	pub const ORANGE: Data = Data{texture_pos:vec2i{x:0,y:1}};
-	pub const BLUE: Data = Data{texture_pos:vec2i{x:1,y:1}};
-	pub const PINK: Data = Data{texture_pos:vec2i{x:2,y:1}};
-	pub const GREEN: Data = Data{texture_pos:vec2i{x:0,y:2}};
-	pub const PURPLE: Data = Data{texture_pos:vec2i{x:1,y:2}};
-	pub const YELLOW: Data = Data{texture_pos:vec2i{x:2,y:2}};
-	
-	pub const SENT_LINE: Data = Data{texture_pos:vec2i{x:3,y:1}};
-	pub const EMPTY_LINE: Data = Data{texture_pos:vec2i{x:3,y:2}};
-	
-	pub fn new(x: i32, y: i32) -> Self {
-		Self {
-			texture_pos: vec2i::new(x,y),
-		}
-	}
-	pub fn is_empty(&self) -> bool {*self == Data::EMPTY}
-}
-
+use crate::block::Data;
 use crate::Mino;
 use crate::game::Well;
 use sdl2::rect::Rect;
@@ -61,8 +32,8 @@ impl<'a> Canvas<'a> {
 			&self.block,
 			
 			Rect::new(
-				data.texture_pos.x * block_size_tex_i32,
-				data.texture_pos.y * block_size_tex_i32,
+				data.get_texture_pos().x * block_size_tex_i32,
+				data.get_texture_pos().y * block_size_tex_i32,
 				self.block_size_tex, self.block_size_tex),
 			
 			Rect::new(
@@ -101,14 +72,14 @@ impl<'a> Canvas<'a> {
 		}
 	}
 	pub fn draw_well(
-		&mut self, canvas: &mut WindowCanvas, origin: vec2i, well: &Well,
+		&mut self, canvas: &mut WindowCanvas, origin: vec2i, well: &Well, hidden_rows: usize,
 		lc_animation: &Option<crate::unit::LCAnimation>, gol_animation: &Option<crate::unit::GOLAnimation>,
 		countdown: Duration, config: &crate::config::Config
 	) {
 		let f = countdown.as_secs_f64() / (if lc_animation.is_some() {config.line_clear_duration} else {config.game_of_life_duration}).as_secs_f64();
-		for y in 0..well.row_len() {
+		for y in hidden_rows..well.row_len() {
 			for x in 0..well.column_len() {
-				let p = vec2i!(x as i32, y as i32);
+				let p = vec2i!(x as i32, (y-hidden_rows) as i32);
 				let data = well[(x,y)];
 				if let Some(lc_animation) = lc_animation {
 					self.draw_block(canvas, origin, &p, &data);
@@ -3,9 +3,9 @@ use sdl2::{event::Event, keyboard::Keycode};
 use std::{fs::File, path::PathBuf};
 use std::io::prelude::*;
 use std::time::Duration;
-use serde::{Serialize,Deserialize};
 
-use crate::myevents;
+use crate::{myevents, InputMethod};
+use crate::mino_controller::{Handling, MinoController, MoveDirection, MoveState, RotDirection, FallState};
 
 #[derive(Default)]
 pub struct Player {
@@ -48,18 +48,6 @@ impl From<sdl2::controller::Axis> for Conbind {
 	fn from(axis: sdl2::controller::Axis) -> Self {Conbind::Axis(axis)}
 }
 
-#[derive(Debug,Clone,Serialize,Deserialize)]
-pub struct InputMethod {
-	pub keyboard: Option<()>,
-	pub controller: Option<usize>,
-}
-impl InputMethod {
-	pub fn new(keyboard: bool, controller: Option<usize>) -> Self {
-		let keyboard = if keyboard {Some(())} else {None};
-		Self {keyboard, controller}
-	}
-}
-
 #[derive(Debug, Default)]
 pub struct Bind {
 	key: Option<Keycode>,
@@ -73,6 +61,10 @@ impl Bind {
 		let con = Some(con);
 		Bind {key, con}
 	}
+	// For things that can only be done with a keyboard, like typing.
+	pub fn from_key(key: Keycode) -> Self {
+		Bind {key: Some(key), con: None}
+	}
 	fn from_name(key_name: Option<&str>, button_name: Option<&str>) -> Self {
 		Bind {
 			key: key_name.and_then(Keycode::from_name),
@@ -110,6 +102,9 @@ pub struct MenuBinds {
 	pub left: Bind, pub right: Bind,
 	pub ok: Bind, pub add_player: Bind,
 	pub pause: Bind, pub restart: Bind,
+	pub ready: Bind, pub chat: Bind,
+	pub fast_forward: Bind, pub step: Bind,
+	pub add_bot: Bind,
 }
 
 #[derive(Debug, Default)]
@@ -169,6 +164,74 @@ impl PlayerBinds {
 			store,
 		}
 	}
+	pub fn update_mino_controller(&self, mino_controller: &mut MinoController, input_method: &InputMethod, event: &Event) {
+		let MinoController {
+			move_direction,
+			move_state,
+			rot_direction,
+			fall_state,
+			store,
+			..
+		} = mino_controller;
+		
+		let b = self;
+		let im = input_method;
+		
+		if b.left.is_down(event, &im) ||
+		b.left_alt.is_down(event, &im) {
+			*move_direction = MoveDirection::Left;
+			*move_state = MoveState::Instant;
+		}
+		
+		if b.right.is_down(event, &im) ||
+		b.right_alt.is_down(event, &im) {
+			*move_direction = MoveDirection::Right;
+			*move_state = MoveState::Instant;
+		}
+		
+		if b.left.is_up(event, &im) ||
+		b.left_alt.is_up(event, &im) {
+			if *move_direction == MoveDirection::Left {
+				*move_direction = MoveDirection::None;
+				*move_state = MoveState::Still;
+			}
+		}
+		
+		if b.right.is_up(event, &im) ||
+		b.right_alt.is_up(event, &im) {
+			if *move_direction == MoveDirection::Right {
+				*move_direction = MoveDirection::None;
+				*move_state = MoveState::Still;
+			}
+		}
+		
+		if b.rot_left.is_down(event, &im) {
+			*rot_direction = RotDirection::Left
+		}
+		
+		if b.rot_right.is_down(event, &im) ||
+		b.rot_right_alt.is_down(event, &im) {
+			*rot_direction = RotDirection::Right
+		}
+		
+		if b.softdrop.is_down(event, &im) ||
+		b.softdrop_alt.is_down(event, &im) {
+			*fall_state = FallState::Softdrop;
+		}
+		
+		if b.softdrop.is_up(event, &im) ||
+		b.softdrop_alt.is_up(event, &im) {
+			*fall_state = FallState::Fall
+		}
+		
+		if b.harddrop.is_down(event, &im) {
+			*fall_state = FallState::Harddrop;
+		}
+		
+		if b.store.is_down(event, &im) {
+			*store = true;
+		}
+	}
 }
 
 pub struct Config {
@@ -184,9 +247,29 @@ pub struct Config {
 	pub line_clear_path: PathBuf,
 	pub line_clear_duration: Duration,
 	pub game_of_life_duration: Duration,
+	pub lock_delay: Duration,
+	pub max_lock_resets: u32,
+	pub gravity: crate::gravity::Kind,
+	pub well_size: crate::game::WellSize,
+	pub top_out_rules: crate::game::TopOutRules,
+	pub randomizer: crate::game::Randomizer,
+	pub preview: crate::unit::Preview,
+	pub hold: crate::unit::Hold,
+	pub name: String,
+	pub input_delay: u64,
+	pub rollback: u64,
 }
 
 impl Config {
+	pub fn get_handling(&self, config_id: usize) -> Handling {
+		let player = &self.players[config_id];
+		Handling {
+			move_prepeat_duration: player.move_prepeat_duration,
+			move_repeat_duration: player.move_repeat_duration,
+			lock_delay: self.lock_delay,
+			max_lock_resets: self.max_lock_resets,
+		}
+	}
 	pub fn from_file() -> Config {
 		let mut file = File::open("config.toml")
 			.expect("Couldn't open config file");
@@ -227,6 +310,35 @@ impl Config {
 		
 		let line_clear_duration = get_duration("line_clear_duration", 0.1);
 		let game_of_life_duration = get_duration("game_of_life_duration", 0.25);
+		let lock_delay = get_duration("lock_delay", 0.5);
+		let max_lock_resets = toml.get("max_lock_resets").and_then(Value::as_integer).unwrap_or(15) as u32;
+		let default_well_size = crate::game::WellSize::default();
+		let get_size = |name, default|toml.get(name).and_then(Value::as_integer).map(|a|a.max(0) as usize).unwrap_or(default);
+		let well_size = crate::game::WellSize {
+			width: get_size("well_width", default_well_size.width),
+			height: get_size("well_height", default_well_size.height),
+			buffer: get_size("buffer_rows", default_well_size.buffer),
+		}.clamp();
+		let default_top_out_rules = crate::game::TopOutRules::default();
+		let get_rule = |name, default|toml.get(name).and_then(Value::as_bool).unwrap_or(default);
+		let top_out_rules = crate::game::TopOutRules {
+			block_out: get_rule("block_out", default_top_out_rules.block_out),
+			lock_out: get_rule("lock_out", default_top_out_rules.lock_out),
+			partial_lock_out: get_rule("partial_lock_out", default_top_out_rules.partial_lock_out),
+		};
+		let default_preview = crate::unit::Preview::default();
+		let preview = crate::unit::Preview {
+			len: toml.get("preview").and_then(Value::as_integer)
+				.map(|a|(a.max(0) as usize).min(crate::unit::Preview::MAX_LEN))
+				.unwrap_or(default_preview.len),
+			hidden: toml.get("hidden_next").and_then(Value::as_bool).unwrap_or(default_preview.hidden),
+		};
+		let hold = toml.get("hold").and_then(Value::as_str).and_then(crate::unit::Hold::from_name).unwrap_or_default();
+		let randomizer = toml.get("randomizer").and_then(Value::as_str).and_then(crate::game::Randomizer::from_name).unwrap_or_default();
+		let gravity = toml.get("gravity").and_then(Value::as_str).and_then(crate::gravity::Kind::from_name).unwrap_or_default();
+		let name = toml.get("name").and_then(Value::as_str).unwrap_or("Player").to_string();
+		let input_delay = toml.get("input_delay").and_then(Value::as_integer).unwrap_or(2).max(0) as u64;
+		let rollback = toml.get("rollback").and_then(Value::as_integer).unwrap_or(0).max(0) as u64;
 		
 		Config {
 			width,
@@ -241,6 +353,17 @@ impl Config {
 			line_clear_path,
 			line_clear_duration,
 			game_of_life_duration,
+			lock_delay,
+			max_lock_resets,
+			gravity,
+			well_size,
+			top_out_rules,
+			randomizer,
+			preview,
+			hold,
+			name,
+			input_delay,
+			rollback,
 		}
 	}
 }
\ No newline at end of file
@@ -11,66 +11,30 @@ use std::{collections::{BTreeSet, VecDeque, BTreeMap}, iter, net::SocketAddr, ti
 use std::thread::sleep;
 use sdl2::pixels::Color;
 use sdl2::rect::Rect;
-use config::{InputMethod,Bind,MenuBinds};
-use network::{NetworkState,NetworkCommand};
+use config::{Bind,MenuBinds};
+use network::{NetworkState,NetworkCommand,NetworkEvent,HandshakeEvent};
 use command::Command;
 
 use itertools::izip;
 
-#[macro_use]
-pub mod vec2;
-pub mod mino;
-pub mod block;
+pub mod canvas;
 pub mod text;
 pub mod config;
-pub mod lenio;
-pub mod game;
-pub mod mino_controller;
-pub mod unit;
-pub mod ui;
-pub mod network;
-pub mod room;
-pub mod command;
 pub mod myevents;
-use vec2::{vec2i,vec2f};
+use tetris_core::{mino, block, lenio, game, rotation, gravity, mino_controller, unit, ui, network, room, lockstep, command};
+use tetris_core::{vec2i, vec2f, State, InputMethod, PlayerKind, Player, MAX_PLAYERS};
+use tetris_core::bot;
 use text::TextCreator;
 use config::Config;
 use unit::{Unit, Mode};
 use std::net::{TcpListener, TcpStream, ToSocketAddrs};
 use lenio::LenIO;
-use serde::{Serialize, Deserialize};
+use lockstep::{Desync, Input, Timing};
+use tetris_core::replay::{Replay, Playback};
 use bincode::{serialize, deserialize};
 use mino::Mino;
-use mino_controller::MinoController;
 use ui::{EnumSelect, GameModeSelection, GameLayout, NetworkStateSelection, Pause, PauseSelection, CenteredLayout, TitleSelection};
 
-pub enum State {
-	Play {
-		players_won: u32,
-		players_done: u32,
-		players_lost: u32,
-		over: bool,
-		winner: Option<String>,
-		pause: Option<Pause>,
-	},
-	Title,
-	PreLobby,
-	Lobby,
-}
-
-impl State {
-	fn play() -> Self {
-		State::Play {
-			players_won: 0,
-			players_done: 0,
-			players_lost: 0,
-			over: false,
-			winner: None,
-			pause: None,
-		}
-	}
-}
-
 struct LinesClearedText<'a>(Texture<'a>, &'a TextCreator<'a,'a>, u32);
 impl<'a> LinesClearedText<'a> {
 	fn new(text_creator: &'a TextCreator, block_size: u32) -> Self {
@@ -81,6 +45,26 @@ impl<'a> LinesClearedText<'a> {
 	}
 }
 
+struct FaultsText<'a>(Texture<'a>, &'a TextCreator<'a,'a>, u32);
+impl<'a> FaultsText<'a> {
+	fn new(text_creator: &'a TextCreator, block_size: u32) -> Self {
+		FaultsText(text_creator.builder("").build(), text_creator, block_size)
+	}
+	fn update(&mut self, faults: u32) {
+		self.0 = self.1.builder(&format!("Faults: {}", faults)).game().with_wrap(self.2*4).build()
+	}
+}
+
+struct ScoreText<'a>(Texture<'a>, &'a TextCreator<'a,'a>, u32);
+impl<'a> ScoreText<'a> {
+	fn new(text_creator: &'a TextCreator, block_size: u32) -> Self {
+		ScoreText(text_creator.builder("").build(), text_creator, block_size)
+	}
+	fn update(&mut self, score: u32) {
+		self.0 = self.1.builder(&format!("Score: {}", score)).game().with_wrap(self.2*4).build()
+	}
+}
+
 struct LevelText<'a>(Texture<'a>, &'a TextCreator<'a,'a>, u32);
 impl<'a> LevelText<'a> {
 	fn new(text_creator: &'a TextCreator, block_size: u32) -> Self {
@@ -184,26 +168,6 @@ impl<'a> Prompt<'a> {
 	}
 }
 
-#[derive(Debug,Clone,Serialize,Deserialize)]
-enum PlayerKind {Local(InputMethod), Network}
-
-impl Default for PlayerKind {fn default() -> Self {PlayerKind::Network}}
-
-#[derive(Debug,Clone,Serialize,Deserialize,Default)]
-pub struct Player {
-	#[serde(skip)]kind: PlayerKind,
-	name: String,
-}
-
-impl Player {
-	fn new(name: String, input: InputMethod) -> Player {
-		Player {
-			kind: PlayerKind::Local(input),
-			name,
-		}
-	}
-}
-
 fn prev_next_variant<T: EnumSelect>(mut value: T, prev: &Bind, next: &Bind, event: &Event, input_method: &InputMethod) -> T {
 	if prev.is_down(event, input_method) {value = value.prev_variant()}
 	if next.is_down(event, input_method) {value = value.next_variant()}
@@ -233,6 +197,33 @@ fn load_saved_unit() -> Option<Unit> {
 	})
 }
 
+fn load_replay() -> Option<Replay> {
+	use std::fs::File;
+	use std::io::prelude::*;
+	let file = File::open("replay");
+	file.ok().and_then(|mut file|{
+		let mut buf = Vec::<u8>::new();
+		file.read_to_end(&mut buf).ok().and_then(|_|{
+			deserialize(&buf).ok()
+		})
+	})
+}
+
+// Writes out the game that the room has been recording, if there is one.
+fn save_replay(room: &mut Room, replay: &mut Option<Replay>) {
+	use std::fs::File;
+	use std::io::prelude::*;
+	if let Some(recorded) = room.replay.take() {
+		// It can still be watched from here, even if it couldn't be saved.
+		let saved = File::create("replay")
+			.and_then(|mut file|file.write_all(&serialize(&recorded).unwrap()));
+		if let Err(err) = saved {
+			println!("Couldn't save the replay: {}", err);
+		}
+		*replay = Some(recorded);
+	}
+}
+
 // lazy_static! {
 // 	static ref LINE_CLEAR_DURATION: Duration = Duration::from_secs_f64(0.2);
 // 	static ref GAME_OF_LIFE_DURATION: Duration = Duration::from_secs_f64(0.25);
@@ -241,8 +232,6 @@ fn load_saved_unit() -> Option<Unit> {
 const MENU_FONT_SIZE: u16 = 32;
 const BIG_FONT_SIZE: u16 = 128;
 
-const MAX_PLAYERS: usize = 8;
-
 fn main() {
 	let sdl_context = sdl2::init()
 		.expect("Failed to initialize sdl2");
@@ -298,7 +287,7 @@ fn main() {
 	let line_clear = texture_creator.load_texture(&config.line_clear_path)
 		.expect("Failed to load line clear texture");
 	
-	let mut block_canvas = block::Canvas::new(block, line_clear, config.block_size_tex, config.block_size_draw, config.line_clear_frames);
+	let mut block_canvas = canvas::Canvas::new(block, line_clear, config.block_size_tex, config.block_size_draw, config.line_clear_frames);
 	
 	let menu_font = ttf_context.load_font("gfx/IBMPlexMono-Regular.otf", MENU_FONT_SIZE)
 		.expect("Failed to load font");
@@ -314,23 +303,60 @@ fn main() {
 	let game_over_text = text_creator.builder("Game over").game().build();
 	let game_won_text = text_creator.builder("You won").game().build();
 	
+	let block_out_text = text_creator.builder("Block out").game().build();
+	let lock_out_text = text_creator.builder("Lock out").game().build();
+	let partial_lock_out_text = text_creator.builder("Partial lock out").game().build();
+	let get_top_out_text = |top_out: &game::TopOut|
+		match *top_out {
+			game::TopOut::BlockOut => &block_out_text,
+			game::TopOut::LockOut => &lock_out_text,
+			game::TopOut::PartialLockOut => &partial_lock_out_text,
+		};
+	let finesse_fault_text = text_creator.builder("Finesse fault").game().build();
+	
 	let host_start_text = text_creator.builder("Press enter to start game")
 		.with_wrap(window_rect.width() as u32).build();
 	let add_player_text = text_creator.builder("Press q to add a player")
 		.with_wrap(window_rect.width() as u32).build();
+	let spectating_text = text_creator.builder("Spectating")
+		.with_wrap(window_rect.width() as u32).build();
 	let waiting_for_host_text = text_creator.builder("Waiting for host to start game...")
 		.with_wrap(window_rect.width() as u32).build();
+	let connecting_text = text_creator.builder("Connecting to host...")
+		.with_wrap(window_rect.width() as u32).build();
+	let mut network_error_text = None;
 	
 	let local_player_text = text_creator.builder(" (Local)").build();
+	let cpu_player_text = text_creator.builder(" (CPU)").build();
 	let network_player_text = text_creator.builder(" (Network)").build();
 	
 	let get_player_text = |player: &Player|{
 		match player.kind {
 			PlayerKind::Local {..} => &local_player_text,
+			PlayerKind::Cpu {..} => &cpu_player_text,
 			PlayerKind::Network {..} => &network_player_text,
 		}
 	};
 	
+	let mut bot_difficulty = bot::Difficulty::default();
+	let easy_bot_text = text_creator.builder("Press b to add an easy bot, or a and d for a harder one")
+		.with_wrap(window_rect.width() as u32).build();
+	let medium_bot_text = text_creator.builder("Press b to add a medium bot, or a and d for another one")
+		.with_wrap(window_rect.width() as u32).build();
+	let hard_bot_text = text_creator.builder("Press b to add a hard bot, or a and d for an easier one")
+		.with_wrap(window_rect.width() as u32).build();
+	let get_add_bot_text = |bot_difficulty: &bot::Difficulty|
+		match bot_difficulty {
+			bot::Difficulty::Easy => &easy_bot_text,
+			bot::Difficulty::Medium => &medium_bot_text,
+			bot::Difficulty::Hard => &hard_bot_text,
+		};
+	let ready_text = text_creator.builder(" (Ready)").build();
+	let lobby_keys_text = text_creator.builder("Press r to get ready, and t to chat")
+		.with_wrap(window_rect.width() as u32).build();
+	let not_ready_text = text_creator.builder("Not everyone is ready, press enter again to start anyway")
+		.with_wrap(window_rect.width() as u32).build();
+	
 	let fps: u32 = 60;
 	let dpf: Duration = Duration::from_secs(1) / fps;
 	
@@ -338,12 +364,24 @@ fn main() {
 	let sprint_text = text_creator.builder("Sprint").build();
 	let versus_text = text_creator.builder("Versus").build();
 	let game_of_life_text = text_creator.builder("Game of life").build();
+	let finesse_text = text_creator.builder("Finesse practice").build();
 	let get_game_mode_text = |selected_game_mode: &GameModeSelection|
 		match *selected_game_mode {
 			GameModeSelection::Marathon => &marathon_text,
 			GameModeSelection::Sprint => &sprint_text,
 			GameModeSelection::Versus => &versus_text,
 			GameModeSelection::GameOfLife => &game_of_life_text,
+			GameModeSelection::Finesse => &finesse_text,
+		};
+	
+	let srs_text = text_creator.builder("SRS rotation").build();
+	let ars_text = text_creator.builder("ARS rotation").build();
+	let nrs_text = text_creator.builder("NRS rotation").build();
+	let get_rotation_system_text = |selected_rotation_system: &rotation::Kind|
+		match *selected_rotation_system {
+			rotation::Kind::Srs => &srs_text,
+			rotation::Kind::Ars => &ars_text,
+			rotation::Kind::Nrs => &nrs_text,
 		};
 	
 	// NETWORK STATE
@@ -353,15 +391,19 @@ fn main() {
 	let offline_text = text_creator.builder("Offline").build();
 	let host_text = text_creator.builder("Host").build();
 	let client_text = text_creator.builder("Client").build();
+	let spectator_text = text_creator.builder("Spectator").build();
 	let get_network_text = |ref selected_network_state: &NetworkStateSelection|
 		match selected_network_state {
 			NetworkStateSelection::Offline => &offline_text,
 			NetworkStateSelection::Host => &host_text,
 			NetworkStateSelection::Client => &client_text,
+			NetworkStateSelection::Spectator => &spectator_text,
 		};
 	
 	let mut lines_cleared_text: Vec<_> = iter::from_fn(||Some(LinesClearedText::new(&text_creator, config.block_size_draw))).take(MAX_PLAYERS).collect();
+	let mut score_text: Vec<_> = iter::from_fn(||Some(ScoreText::new(&text_creator, config.block_size_draw))).take(MAX_PLAYERS).collect();
 	let mut level_text: Vec<_> = iter::from_fn(||Some(LevelText::new(&text_creator, config.block_size_draw))).take(MAX_PLAYERS).collect();
+	let mut faults_text: Vec<_> = iter::from_fn(||Some(FaultsText::new(&text_creator, config.block_size_draw))).take(MAX_PLAYERS).collect();
 	
 	let can_continue_text = text_creator.builder("Continue").build();
 	let cant_continue_text = text_creator.builder("Continue").color(Color::GRAY).build();
@@ -372,6 +414,19 @@ fn main() {
 		else {&cant_continue_text}
 	};
 	
+	let can_watch_replay_text = text_creator.builder("Watch replay").build();
+	let cant_watch_replay_text = text_creator.builder("Watch replay").color(Color::GRAY).build();
+	let mut replay = load_replay();
+	// Set while a replay is being watched.
+	let mut playback: Option<Playback> = None;
+	
+	let get_watch_replay_text = |can_watch_replay|{
+		if can_watch_replay {&can_watch_replay_text}
+		else {&cant_watch_replay_text}
+	};
+	let replay_text = text_creator.builder("Replay: enter to pause, f to fast-forward").build();
+	let replay_paused_text = text_creator.builder("Replay paused: enter to resume, . to step").build();
+	
 	let mut quick_game = true;
 	let new_game_text = text_creator.builder("New Game").build();
 	let quick_game_text = text_creator.builder("Quick Game").build();
@@ -407,6 +462,11 @@ fn main() {
 		add_player: Bind::new(Keycode::Q, (Button::Back).into()), // definitely remove controller bind
 		pause:      Bind::new(Keycode::Escape, (Button::Start).into()),
 		restart:    Bind::new(Keycode::R, (Button::Back).into()), // maybe change/remove controller bind
+		ready:      Bind::new(Keycode::R, (Button::Y).into()),
+		chat:       Bind::from_key(Keycode::T),
+		fast_forward: Bind::new(Keycode::F, (Button::RightShoulder).into()),
+		step:       Bind::new(Keycode::Period, (Button::LeftShoulder).into()),
+		add_bot:    Bind::from_key(Keycode::B),
 	};
 	
 	let mut name_prompt = Prompt::new(&text_creator, "Name");
@@ -414,7 +474,25 @@ fn main() {
 	
 	let mut adding_player = false;
 	
+	let mut chat_prompt = Prompt::new(&text_creator, "Chat");
+	let mut chatting = false;
+	let mut chat_texts = Vec::<Texture>::new();
+	// Set once the host tried to start before everyone was ready.
+	let mut waiting_for_ready = false;
+	
 	let mut room = Room::new();
+	room.gravity = config.gravity;
+	room.well_size = config.well_size;
+	room.top_out_rules = config.top_out_rules;
+	room.randomizer = config.randomizer;
+	room.preview = config.preview;
+	room.hold = config.hold;
+	room.input_delay = config.input_delay;
+	room.timing = Timing {
+		frame_duration: dpf,
+		line_clear_duration: config.line_clear_duration,
+		game_of_life_duration: config.game_of_life_duration,
+	};
 	let mut commands = VecDeque::new();
 	
 	let mut player = Player::default();
@@ -496,7 +574,26 @@ fn main() {
 			// Some aliases to make things simpler
 			let mb = &menu_binds;
 			let im = InputMethod::new(true, Some(0));
+			let spectating = selected_network_state == NetworkStateSelection::Spectator;
 			match state {
+				// The chat prompt takes over the keyboard until the message is
+				// sent or dropped.
+				_ if chatting => {
+					chat_prompt.input(&event);
+					let send = mb.ok.is_down(&event, &im);
+					if send || mb.pause.is_down(&event, &im) {
+						if send && !chat_prompt.text.is_empty() {
+							commands.push_back(RoomCommand::Chat {sender: config.name.clone(), text: chat_prompt.text.clone()}.wrap());
+						}
+						chat_prompt = Prompt::new(&text_creator, "Chat");
+						chatting = false;
+						video_subsystem.text_input().stop();
+					}
+				}
+				State::Play {over: true, pause: None, ..} if room.online && !spectating && playback.is_none() && mb.chat.is_down(&event, &im) => {
+					chatting = true;
+					video_subsystem.text_input().start();
+				}
 				State::Play {ref mut pause,..} => {
 					
 					if mb.pause.is_down(&event, &im) {
@@ -504,7 +601,12 @@ fn main() {
 						just_saved = false;
 					}
 					if mb.restart.is_down(&event, &im) {
-						commands.push_back(RoomCommand::StartGame.wrap());
+						if let Some(playback) = &mut playback {
+							commands.extend(playback.start().into_iter().map(RoomCommand::wrap));
+						}else {
+							save_replay(&mut room, &mut replay);
+							commands.push_back(RoomCommand::StartGame(rand::random()).wrap());
+						}
 					}
 					
 					if let Some(Pause{selection}) = pause {
@@ -514,7 +616,7 @@ fn main() {
 							match selection {
 								PauseSelection::Resume => *pause = None,
 								PauseSelection::Save => {
-									if let NetworkState::Offline = network_state {
+									if let (NetworkState::Offline, None) = (&network_state, &playback) {
 										use std::fs::File;
 										use std::io::prelude::*;
 										let mut file = File::create("save").unwrap();
@@ -523,11 +625,22 @@ fn main() {
 									}
 								}
 								PauseSelection::Restart => {
-									commands.push_back(RoomCommand::StartGame.wrap());
+									if let Some(playback) = &mut playback {
+										commands.extend(playback.start().into_iter().map(RoomCommand::wrap));
+									}else {
+										save_replay(&mut room, &mut replay);
+										commands.push_back(RoomCommand::StartGame(rand::random()).wrap());
+									}
 								}
 								PauseSelection::QuitToTitle => {
+									if playback.take().is_none() {
+										save_replay(&mut room, &mut replay);
+									}
 									state = State::Title;
 									room.players.clear();
+									room.units.clear();
+									room.chat.clear();
+									chat_texts.clear();
 									player_names_text.clear();
 									saved_unit = load_saved_unit();
 								}
@@ -536,11 +649,21 @@ fn main() {
 								}
 							}
 						}
+					}else if let Some(playback) = &mut playback {
+						if mb.ok.is_down(&event, &im) {
+							playback.paused = !playback.paused;
+						}
+						if mb.fast_forward.is_down(&event, &im) {
+							playback.fast_forward = !playback.fast_forward;
+						}
+						if playback.paused && mb.step.is_down(&event, &im) {
+							playback.step();
+						}
 					}else {
 						for (unit, player) in izip!(&mut room.units, &room.players) {
 							if let unit::Kind::Local{mino_controller,..} = &mut unit.kind {
 								if let PlayerKind::Local(input_method) = &player.kind {
-									mino_controller.update(&config.binds, input_method, &event);
+									config.binds[mino_controller.config_id].update_mino_controller(mino_controller, input_method, &event);
 								}
 							}
 						}
@@ -563,11 +686,23 @@ fn main() {
 								}
 							}
 						},
+						WatchReplay => {
+							if mb.ok.is_down(&event, &im) {
+								if let (Some(replay), NetworkStateSelection::Offline) = (&replay, &selected_network_state) {
+									network_state = NetworkState::Offline;
+									
+									let mut new_playback = Playback::new(replay.clone());
+									commands.extend(new_playback.start().into_iter().map(RoomCommand::wrap));
+									playback = Some(new_playback);
+								}
+							}
+						},
 						NewGame => {
 							if mb.ok.is_down(&event, &im) {
+								room.online = !quick_game && selected_network_state != NetworkStateSelection::Offline;
 								if quick_game {
 									commands.push_back(RoomCommand::AddPlayer(Player::new(String::from(""), InputMethod::new(true, Some(0)))).wrap());
-									commands.push_back(RoomCommand::StartGame.wrap());
+									commands.push_back(RoomCommand::StartGame(rand::random()).wrap());
 								}else {
 									match selected_network_state {
 										NetworkStateSelection::Offline => {
@@ -577,7 +712,7 @@ fn main() {
 											video_subsystem.text_input().start();
 											state = State::PreLobby;
 										}
-										NetworkStateSelection::Client => {
+										NetworkStateSelection::Client | NetworkStateSelection::Spectator => {
 											video_subsystem.text_input().start();
 											state = State::PreLobby;
 										}
@@ -593,6 +728,10 @@ fn main() {
 							room.selected_game_mode = prev_next_variant(
 								room.selected_game_mode, &mb.left, &mb.right, &event, &im);
 						},
+						RotationSystem => {
+							room.selected_rotation_system = prev_next_variant(
+								room.selected_rotation_system, &mb.left, &mb.right, &event, &im);
+						},
 						NetworkMode => {
 							selected_network_state = prev_next_variant(
 								selected_network_state, &mb.left, &mb.right, &event, &im);
@@ -612,17 +751,24 @@ fn main() {
 								
 								NetworkState::Host {
 									listener,
-									streams: Vec::new(),
+									peers: Vec::new(),
+									pending: Vec::new(),
 								}
 							}
-							NetworkStateSelection::Client => {
+							NetworkStateSelection::Client | NetworkStateSelection::Spectator => {
 								let stream = TcpStream::connect(addr).unwrap();
 								stream.set_nonblocking(true).unwrap();
-								let stream = LenIO::new(stream);
-								println!("Connection to host established");
+								network_error_text = None;
 								
-								NetworkState::Client {
-									stream,
+								let spectator = selected_network_state == NetworkStateSelection::Spectator;
+								match NetworkState::connect(stream, addr, &config.name, spectator) {
+									Ok(network_state) => network_state,
+									Err(err) => {
+										println!("Connection to host failed: {}", err);
+										network_error_text = Some(text_creator.builder("Connection to host failed")
+											.with_wrap(window_rect.width() as u32).build());
+										NetworkState::Offline
+									}
 								}
 							}
 						};
@@ -644,10 +790,38 @@ fn main() {
 					}else {
 						if let NetworkState::Host {..} | NetworkState::Offline = network_state {
 							if mb.ok.is_down(&event, &im) {
-								commands.push_back(RoomCommand::StartGame.wrap());
+								// Pressing it a second time starts anyway.
+								if room.is_ready() || waiting_for_ready {
+									commands.push_back(RoomCommand::StartGame(rand::random()).wrap());
+								}
+								waiting_for_ready = true;
+							}
+						}
+						if room.online && !spectating {
+							if mb.chat.is_down(&event, &im) {
+								chatting = true;
+								video_subsystem.text_input().start();
+							}else if mb.ready.is_down(&event, &im) {
+								let local: Vec<usize> = room.players.iter().enumerate()
+									.filter(|(_, player)|matches!(player.kind, PlayerKind::Local(_)))
+									.map(|(index, _)|index)
+									.collect();
+								let ready = !local.iter().all(|&index|room.players[index].ready);
+								for index in local {
+									commands.push_back(RoomCommand::SetReady(index, ready).wrap());
+								}
 							}
 						}
-						if let Some(myevents::MyControllerButtonDown {which, ..}) = myevents::as_user_event_type::<_>(&event) {
+						// Controllers are kept for adding players.
+						let keyboard = InputMethod::new(true, None);
+						if spectating {
+							// Spectators can't add players.
+						}else if mb.add_bot.is_down(&event, &keyboard) {
+							let bots = room.players.iter().filter(|player|matches!(player.kind, PlayerKind::Cpu(_))).count();
+							commands.push_back(RoomCommand::AddPlayer(Player::cpu(format!("Bot {}", bots+1), bot_difficulty)).wrap());
+						}else if mb.left.is_down(&event, &keyboard) || mb.right.is_down(&event, &keyboard) {
+							bot_difficulty = prev_next_variant(bot_difficulty, &mb.left, &mb.right, &event, &keyboard);
+						}else if let Some(myevents::MyControllerButtonDown {which, ..}) = myevents::as_user_event_type::<_>(&event) {
 							let mut not_in_use = true;
 							for player in &room.players {
 								if let Player{kind:PlayerKind::Local(InputMethod{controller:Some(index),..}),..} = player {
@@ -680,30 +854,42 @@ fn main() {
 		// @network
 		let mut network_command_pump = crate::network::NetworkPump::new();
 		
-		while let Some(command) = network_command_pump.poll(&mut network_state) {
-			match command {
-				NetworkCommand::RoomCommand(command) =>
+		while let Some(event) = network_command_pump.poll(&mut network_state) {
+			match event {
+				NetworkEvent::Command(NetworkCommand::RoomCommand(command)) =>
 				commands.push_back(command),
-				NetworkCommand::UnitCommand(command) =>
-				room.commands[command.inner.0].push_back(command.map(|c|c.1)),
+				NetworkEvent::Command(NetworkCommand::Spectate) => {}
+				NetworkEvent::ClientLeft(addr) => {
+					println!("Connection to client lost");
+					network_players -= 1;
+					// Backwards, so that removing a player doesn't move the ones
+					// that are still to be removed.
+					for (index, player) in room.players.iter().enumerate().rev() {
+						if player.peer == Some(addr) {
+							commands.push_back(RoomCommand::DisconnectPlayer(index).wrap());
+						}
+					}
+				}
+				NetworkEvent::HostLeft => {
+					println!("Connection to host lost");
+					network_error_text = Some(text_creator.builder("Connection to host lost")
+						.with_wrap(window_rect.width() as u32).build());
+					for (index, player) in room.players.iter().enumerate().rev() {
+						if matches!(player.kind, PlayerKind::Network) {
+							commands.push_back(RoomCommand::DisconnectPlayer(index).wrap());
+						}
+					}
+				}
 			}
 		}
 		
-		if let (State::Lobby, NetworkState::Host {listener, streams}) =
+		// Spectators can also join in the middle of a game.
+		if let (State::Lobby | State::Play {..}, NetworkState::Host {listener, pending, ..}) =
 		(&state, &mut network_state) {
 			while let Ok(incoming) = listener.accept() {
-				network_players += 1;
-				let mut stream = LenIO::new(incoming.0);
-				
-				stream.write(
-					&serialize(
-						&NetworkCommand::from(RoomCommand::Init(room.clone()).wrap())
-					).unwrap()
-				).unwrap();
-				
-				streams.push(stream);
+				incoming.0.set_nonblocking(true).unwrap();
+				pending.push((LenIO::new(incoming.0), incoming.1));
 				println!("{:?}", incoming.1);
-				println!("Connection to client established");
 			}
 		}
 		
@@ -721,9 +907,20 @@ fn main() {
 				}
 			}
 			if room.just_started {
-				for (unit, lines_cleared_text, level_text) in
-				izip!(&room.units, &mut lines_cleared_text, &mut level_text) {
+				waiting_for_ready = false;
+			}
+			if room.just_chatted || room.just_initted {
+				chat_texts = room.chat.iter()
+					.map(|(sender, text)|text_creator.builder(&format!("{}: {}", sender, text))
+						.with_wrap(window_rect.width() as u32).build())
+					.collect();
+			}
+			if room.just_started || room.just_initted {
+				for (unit, lines_cleared_text, score_text, level_text, faults_text) in
+				izip!(&room.units, &mut lines_cleared_text, &mut score_text, &mut level_text, &mut faults_text) {
 					lines_cleared_text.update(unit.base.lines_cleared);
+					score_text.update(unit.base.score);
+					faults_text.update(unit.base.finesse_faults);
 					if let Mode::Marathon {level,..} = &unit.base.mode {level_text.update(*level)}
 				}
 			}
@@ -733,26 +930,66 @@ fn main() {
 			room.reset_flags();
 		}
 		
-		// UNITS
-		if let State::Play {over,pause,players_lost,players_won,winner,..} = &mut state {
-			for unit_id in 0..room.units.len() {
-				let unit = &mut room.units[unit_id];
-				if let unit::State::Animation {countdown} = &mut unit.base.state {
-					*countdown += dpf;
-					if unit.base.lc_animation.is_some() {
-						if *countdown >= config.line_clear_duration {
-							unit.base.state = unit::State::Play;
+		// After the room commands, so that the room sent in Init has all of
+		// the commands that came in so far.
+		if let State::Lobby | State::Play {..} = state {
+			let is_host = matches!(network_state, NetworkState::Host {..});
+			for event in network_state.poll_handshakes(&config.name, &room) {
+				match event {
+					HandshakeEvent::Accepted(name) => {
+						if is_host {
+							network_players += 1;
+							println!("Connection to client {} established", name);
+						}else {
+							println!("Connection to host {} established", name);
 						}
-					}else if unit.base.gol_animation.is_some() {
-						if *countdown >= config.game_of_life_duration {
-							unit.base.state = unit::State::Play;
+					}
+					HandshakeEvent::Rejected(reason) => {
+						println!("Connection rejected: {}", reason);
+						if !is_host {
+							network_error_text = Some(text_creator.builder(&format!("Connection rejected: {}", reason))
+								.with_wrap(window_rect.width() as u32).build());
 						}
 					}
 				}
-				
+			}
+		}
+		
+		// INPUTS
+		let mut inputs = Vec::new();
+		if let State::Play {pause,..} = &state {
+			let not_paused = !pause.is_some() || network_players > 0;
+			let lockstep = &mut room.lockstep;
+			if not_paused && lockstep.can_input(config.rollback) {
+				for (unit_id, unit) in izip!(0.., &mut room.units) {
+					// The controller goes by the unit that the player sees.
+					let base = lockstep.predicted.as_ref().map_or(&unit.base, |units|&units[unit_id].base);
+					let mut commands = Vec::new();
+					match &mut unit.kind {
+						unit::Kind::Local {mino_controller} => {
+							if let unit::State::Play = base.state {
+								let handling = config.get_handling(mino_controller.config_id);
+								mino_controller.append_commands(&mut commands, &handling, base, dpf);
+							}
+						}
+						unit::Kind::Cpu {bot} => bot.append_commands(&mut commands, base, dpf),
+						unit::Kind::Network => continue,
+					}
+					inputs.push(Input {unit_id, frame: lockstep.input_frame, commands});
+				}
+				lockstep.input_frame += 1;
+			}
+		}
+		for input in inputs {
+			RoomCommand::Input(input).wrap().execute(&mut network_state, |_|{}, (&mut room, &mut state));
+		}
+		
+		// UNITS
+		if let State::Play {over,pause,players_lost,players_won,winner,..} = &mut state {
+			if !room.units.is_empty() {
 				let players = room.players.len() as u32;
 				match room.selected_game_mode {
-					GameModeSelection::Marathon | GameModeSelection::Sprint | GameModeSelection::GameOfLife =>
+					GameModeSelection::Marathon | GameModeSelection::Sprint | GameModeSelection::GameOfLife | GameModeSelection::Finesse =>
 					if *players_won == players {*over = true}
 					GameModeSelection::Versus =>
 					if *players_lost == players-1 {
@@ -768,49 +1005,67 @@ fn main() {
 			
 			let not_paused = !pause.is_some() || network_players > 0;
 			if not_paused {
-				for (unit_id, unit) in izip!(0.., &mut room.units) {
-					if let unit::Kind::Local {mino_controller,..} = &mut unit.kind {
-						if let unit::State::Play = unit.base.state {
-							mino_controller.append_commands(&mut room.commands[unit_id], &config.players, dpf);
-						}
-					}
+				if let Some(playback) = &mut playback {
+					playback.update(&mut room);
+				}else {
+					room.lockstep.advance(&mut room.units, &mut room.commands, &room.timing);
+				}
+				if config.rollback > 0 && playback.is_none() {
+					room.lockstep.predict(&room.units, &room.commands, &room.timing);
 				}
 				
-				// We loop as long as there are new commands
-				let mut keep_looping = true;
-				while keep_looping {
-					keep_looping = false;
-					for (unit_id, unit) in izip!(0usize.., &mut room.units) {
-						let commands = &mut room.commands;
-						
-						// This while loop is ugly. Refactor it when this
-						// https://github.com/rust-lang/rust/issues/53667 gets added
-						while !commands[unit_id].is_empty() && matches!(unit.base.state, unit::State::Play) {
-							keep_looping = true;
-							let command = commands[unit_id].pop_front().unwrap();
-							let command = command.map(|c|(unit_id, c));
-							// println!("{:?}", command);
-							let append = |c: command::CommandWrapper<unit::UnitCommandInner>|commands[c.inner.0].push_back(c.map(|c|c.1));
-							command.execute(&mut network_state, append, unit);
+				for checksum in room.lockstep.new_checksums.drain(..) {
+					commands.push_back(RoomCommand::Checksum(checksum).wrap());
+				}
+				let mut desyncs: Vec<_> = room.lockstep.desyncs.drain(..).collect();
+				desyncs.sort();
+				desyncs.dedup_by_key(|desync|(desync.frame, desync.unit_id));
+				for Desync {frame, unit_id, local, remote} in desyncs {
+					println!(
+						"Desync on frame {} in unit {} ({}): checksum is {:016x} here, and {:016x} on the other side",
+						frame, unit_id, room.players[unit_id].name, local, remote);
+					// The host has the last word on what the units look like.
+					if let NetworkState::Host {..} = network_state {
+						if let Some(sync) = room.lockstep.get_sync(frame, unit_id) {
+							println!("Sending unit {} as it was on frame {}", unit_id, frame);
+							commands.push_back(RoomCommand::Sync(sync).wrap());
 						}
 					}
 				}
+				if room.lockstep.just_synced {
+					room.lockstep.just_synced = false;
+					*players_lost = room.units.iter().filter(|unit|matches!(unit.base.state, unit::State::Lose)).count() as u32;
+					*players_won = room.units.iter().filter(|unit|matches!(unit.base.state, unit::State::Win)).count() as u32;
+					for (unit, lines_cleared_text, score_text, level_text, faults_text) in
+					izip!(&room.units, &mut lines_cleared_text, &mut score_text, &mut level_text, &mut faults_text) {
+						lines_cleared_text.update(unit.base.lines_cleared);
+						score_text.update(unit.base.score);
+						faults_text.update(unit.base.finesse_faults);
+						if let Mode::Marathon {level,..} = &unit.base.mode {level_text.update(*level)}
+					}
+				}
 				
-				for (unit, lines_cleared_text, level_text, player) in
-				izip!(&mut room.units, &mut lines_cleared_text, &mut level_text, &room.players) {
+				for (unit, lines_cleared_text, score_text, level_text, faults_text, player) in
+				izip!(&mut room.units, &mut lines_cleared_text, &mut score_text, &mut level_text, &mut faults_text, &room.players) {
 					if unit.base.just_cleared_lines {
 						lines_cleared_text.update(unit.base.lines_cleared);
 					}
+					if unit.base.just_faulted {
+						faults_text.update(unit.base.finesse_faults);
+					}
+					if unit.base.just_changed_score {
+						score_text.update(unit.base.score);
+					}
 					if unit.base.just_changed_mino {
 						if let unit::Kind::Local {mino_controller,..} = &mut unit.kind {
 							mino_controller.fall_countdown = Duration::from_secs(0);
 						}
 					}
 					if unit.base.just_changed_level {
+						if let unit::Kind::Local {mino_controller,..} = &mut unit.kind {
+							mino_controller.fall_duration = unit.base.get_fall_duration();
+						}
 						if let Mode::Marathon {level,..} = &unit.base.mode {
-							if let unit::Kind::Local {mino_controller,..} = &mut unit.kind {
-								mino_controller.fall_duration = unit::get_level_fall_duration(*level);
-							}
 							level_text.update(*level);
 						}
 					}
@@ -824,6 +1079,12 @@ fn main() {
 					unit.base.reset_flags();
 				}
 			}
+			
+			// The game gets saved once there's nothing left to happen in it.
+			let done = room.units.iter().all(|unit|matches!(unit.base.state, unit::State::Lose | unit::State::Win));
+			if (*over || done) && playback.is_none() {
+				save_replay(&mut room, &mut replay);
+			}
 		}
 		
 		
@@ -863,6 +1124,15 @@ fn main() {
 					layout.row(height as i32);
 					layout.row_margin(15);
 					
+					let watch_replay_text = get_watch_replay_text(replay.is_some() && selected_network_state == NetworkStateSelection::Offline);
+					let (width, height) = get_texture_dim(&watch_replay_text);
+					let rect = Rect::new(layout.centered_x(width), layout.y, width, height);
+					f!(canvas, {draw_same_scale(canvas, &watch_replay_text, rect)});
+					f!(canvas, {select(canvas, rect, matches!(title_selection, TitleSelection::WatchReplay))});
+					
+					layout.row(height as i32);
+					layout.row_margin(15);
+					
 					let game_text = get_game_text(quick_game);
 					let (width, height) = get_texture_dim(&game_text);
 					let rect = Rect::new(layout.centered_x(width), layout.y, width, height);
@@ -878,6 +1148,15 @@ fn main() {
 					f!(canvas, {draw_same_scale(canvas, &game_mode_text, rect)});
 					f!(canvas, {select(canvas, rect, matches!(title_selection, TitleSelection::GameMode))});
 					
+					layout.row(height as i32);
+					layout.row_margin(15);
+					
+					let rotation_system_text = get_rotation_system_text(&room.selected_rotation_system);
+					let (width, height) = get_texture_dim(&rotation_system_text);
+					let rect = Rect::new(layout.centered_x(width), layout.y, width, height);
+					f!(canvas, {draw_same_scale(canvas, &rotation_system_text, rect)});
+					f!(canvas, {select(canvas, rect, matches!(title_selection, TitleSelection::RotationSystem))});
+					
 					if !quick_game {
 						layout.row(height as i32);
 						layout.row_margin(15);
@@ -895,6 +1174,8 @@ fn main() {
 				State::Lobby {..} => {
 					if adding_player {
 						f!(canvas, {name_prompt.draw(canvas)});
+					}else if chatting {
+						f!(canvas, {chat_prompt.draw(canvas)});
 					}else {
 						let mut y = 0;
 						
@@ -913,14 +1194,55 @@ fn main() {
 								
 								y += height as i32;
 							}
+							NetworkState::Connecting {..} => {
+								let (width, height) = get_texture_dim(&connecting_text);
+								let rect = Rect::new(0, y, width, height);
+								f!(canvas, {draw_same_scale(canvas, &connecting_text, rect)});
+								
+								y += height as i32;
+							}
+						}
+						
+						if let Some(network_error_text) = &network_error_text {
+							let (width, height) = get_texture_dim(network_error_text);
+							let rect = Rect::new(0, y, width, height);
+							f!(canvas, {draw_same_scale(canvas, network_error_text, rect)});
+							
+							y += height as i32;
 						}
 						
-						let (width, height) = get_texture_dim(&add_player_text);
+						let add_player_text = if selected_network_state == NetworkStateSelection::Spectator {&spectating_text} else {&add_player_text};
+						let (width, height) = get_texture_dim(add_player_text);
 						let rect = Rect::new(0, y, width, height);
-						f!(canvas, {draw_same_scale(canvas, &add_player_text, rect)});
+						f!(canvas, {draw_same_scale(canvas, add_player_text, rect)});
 						
 						y += height as i32;
 						
+						if selected_network_state != NetworkStateSelection::Spectator {
+							let add_bot_text = get_add_bot_text(&bot_difficulty);
+							let (width, height) = get_texture_dim(add_bot_text);
+							let rect = Rect::new(0, y, width, height);
+							f!(canvas, {draw_same_scale(canvas, add_bot_text, rect)});
+							
+							y += height as i32;
+						}
+						
+						if room.online && selected_network_state != NetworkStateSelection::Spectator {
+							let (width, height) = get_texture_dim(&lobby_keys_text);
+							let rect = Rect::new(0, y, width, height);
+							f!(canvas, {draw_same_scale(canvas, &lobby_keys_text, rect)});
+							
+							y += height as i32;
+						}
+						
+						if waiting_for_ready {
+							let (width, height) = get_texture_dim(&not_ready_text);
+							let rect = Rect::new(0, y, width, height);
+							f!(canvas, {draw_same_scale(canvas, &not_ready_text, rect)});
+							
+							y += height as i32;
+						}
+						
 						for (player, name_text) in izip!(&room.players, &player_names_text) {
 							let mut x = 0;
 							
@@ -935,11 +1257,27 @@ fn main() {
 							let rect = Rect::new(x, y, width, height);
 							f!(canvas, {draw_same_scale(canvas, &player_text, rect)});
 							
+							x += width as i32;
+							
+							if player.ready {
+								let (width, height) = get_texture_dim(&ready_text);
+								let rect = Rect::new(x, y, width, height);
+								f!(canvas, {draw_same_scale(canvas, &ready_text, rect)});
+							}
+							
+							y += height as i32;
+						}
+						
+						for chat_text in &chat_texts {
+							let (width, height) = get_texture_dim(chat_text);
+							let rect = Rect::new(0, y, width, height);
+							f!(canvas, {draw_same_scale(canvas, chat_text, rect)});
+							
 							y += height as i32;
 						}
 					}
 				}
-				State::Play {pause,..} => {
+				State::Play {pause,over,..} => {
 					let bs = config.block_size_draw as i32;
 					let hbs = bs/2;
 					
@@ -949,17 +1287,20 @@ fn main() {
 						expected_width: expected_game_width,
 					};
 					
-					for (unit, lines_cleared_text, level_text)
-					in izip!(&mut room.units, &lines_cleared_text, &level_text) {
-						let Unit {base: unit::Base {stored_mino, falling_mino, well, state, mode, gol_animation, lc_animation, ..}, kind} = unit;
+					for (unit, lines_cleared_text, score_text, level_text, faults_text)
+					in izip!(room.lockstep.predicted.as_mut().unwrap_or(&mut room.units), &lines_cleared_text, &score_text, &level_text, &faults_text) {
+						let Unit {base: unit::Base {stored_minos, falling_mino, well, well_size, queue, preview, state, mode, gol_animation, lc_animation, top_out_reason, finesse_faults, ..}, kind} = unit;
 						
 						layout.row_margin(hbs);
 						
-						if let Some(ref stored_mino) = stored_mino {
-							f!(canvas, {block_canvas.draw_mino_centered(canvas, layout.as_vec2i(), stored_mino, vec2i!(4,3))});
+						let hold = mode.get_hold();
+						for slot in 0..hold.get_slots() {
+							if let Some(stored_mino) = stored_minos.get(slot) {
+								f!(canvas, {block_canvas.draw_mino_centered(canvas, layout.as_vec2i(), stored_mino, vec2i!(4,3))});
+							}
+							layout.row(3*bs);
+							layout.row_margin(hbs);
 						}
-						layout.row(3*bs);
-						layout.row_margin(hbs);
 						
 						let (width, height) = get_texture_dim(&lines_cleared_text.0);
 						let rect = Rect::new(layout.x(), layout.y(), width, height);
@@ -968,6 +1309,20 @@ fn main() {
 						layout.row(height as i32);
 						layout.row_margin(hbs);
 						
+						let (width, height) = get_texture_dim(&score_text.0);
+						let rect = Rect::new(layout.x(), layout.y(), width, height);
+						f!(canvas, {draw_same_scale(canvas, &score_text.0, rect)});
+						
+						layout.row(height as i32);
+						layout.row_margin(hbs);
+						
+						let (width, height) = get_texture_dim(&faults_text.0);
+						let rect = Rect::new(layout.x(), layout.y(), width, height);
+						f!(canvas, {draw_same_scale(canvas, &faults_text.0, rect)});
+						
+						layout.row(height as i32);
+						layout.row_margin(hbs);
+						
 						if let Mode::Marathon {..} = mode {
 							let (width, height) = get_texture_dim(&level_text.0);
 							let rect = Rect::new(layout.x(), layout.y(), width, height);
@@ -977,10 +1332,15 @@ fn main() {
 						layout.col(4*bs);
 						layout.col_margin(hbs);
 						
+						// Only the two hidden rows closest to the visible part of
+						// the well get space on the screen.
+						let shown_buffer = well_size.buffer.min(2) as i32;
+						
 						if let Mode::Versus {lines_received_sum,..} = mode {
 							layout.row_margin(hbs);
-							for y in 0..well.num_columns() {
-								let data = if well.num_columns()-y > *lines_received_sum as usize {
+							layout.row(shown_buffer*bs);
+							for y in 0..well_size.height {
+								let data = if well_size.height-y > *lines_received_sum as usize {
 									block::Data::EMPTY_LINE
 								}else {
 									block::Data::SENT_LINE
@@ -991,19 +1351,23 @@ fn main() {
 						}
 						
 						layout.row_margin(hbs);
+						layout.row(shown_buffer*bs);
 						
 						let well_rect = Rect::new(
 							layout.x(), layout.y(),
-							well.num_rows() as u32 * bs as u32,
-							well.num_columns() as u32 * bs as u32,
+							well_size.width as u32 * bs as u32,
+							well_size.height as u32 * bs as u32,
 						);
 						
 						let countdown = if let unit::State::Animation {countdown} = state {*countdown} else {Duration::from_secs(0)};
 						
-						f!(canvas, {block_canvas.draw_well(canvas, layout.as_vec2i(), &well, &lc_animation, &gol_animation, countdown, &config)});
+						f!(canvas, {block_canvas.draw_well(canvas, layout.as_vec2i(), &well, well_size.buffer, &lc_animation, &gol_animation, countdown, &config)});
+						
+						// Minos are drawn relative to the top of the hidden rows.
+						let origin = layout.as_vec2i() - vec2i!(0, well_size.buffer as i32*bs);
 						if let Some(falling_mino) = falling_mino {
 							let shadow_mino = game::create_shadow_mino(falling_mino, &well);
-							f!(canvas, {block_canvas.draw_mino(canvas, layout.as_vec2i(), &shadow_mino)});
+							f!(canvas, {block_canvas.draw_mino(canvas, origin, &shadow_mino)});
 							
 							let do_ease = game::may_down_mino(&falling_mino, &well);
 							let mut f = 0f32;
@@ -1015,16 +1379,18 @@ fn main() {
 								}
 							};
 							
-							f!(canvas, {block_canvas.draw_mino(canvas, layout.as_vec2i() + vec2i!(0, (f*bs as f32) as i32), falling_mino)});
+							f!(canvas, {block_canvas.draw_mino(canvas, origin + vec2i!(0, (f*bs as f32) as i32), falling_mino)});
 						}
 						
-						layout.col(10*bs);
+						layout.col(well_size.width as i32*bs);
 						layout.col_margin(hbs);
 						
 						layout.row_margin(hbs);
-						if let unit::Kind::Local {rng: unit::LocalMinoRng {queue,..},..} = &unit.kind {
+						if preview.len > 0 {
 							for mino in queue.iter() {
-								f!(canvas, {block_canvas.draw_mino_centered(canvas, layout.as_vec2i(), mino, vec2i!(4,3))});
+								if !preview.hidden {
+									f!(canvas, {block_canvas.draw_mino_centered(canvas, layout.as_vec2i(), mino, vec2i!(4,3))});
+								}
 								layout.row(3*bs);
 								layout.row_margin(hbs);
 							}
@@ -1041,12 +1407,42 @@ fn main() {
 							unit::State::Lose => {
 								f!(canvas, {darken(canvas, Some(well_rect))});
 								f!(canvas, {draw_centered(canvas, &game_over_text, well_rect)});
+								let (_, height) = get_texture_dim(&game_over_text);
+								let mut rect = well_rect;
+								rect.offset(0, height as i32);
+								if let Some(top_out_reason) = top_out_reason {
+									f!(canvas, {draw_centered(canvas, get_top_out_text(top_out_reason), rect)});
+								}else if let (Mode::Finesse {..}, 1..) = (mode, *finesse_faults) {
+									f!(canvas, {draw_centered(canvas, &finesse_fault_text, rect)});
+								}
 							}
 							_ => {}
 						}
 					}
 					expected_game_width = layout.x;
 					
+					if let Some(playback) = &playback {
+						let replay_text = if playback.paused {&replay_paused_text} else {&replay_text};
+						let (width, height) = get_texture_dim(replay_text);
+						let rect = Rect::new(0, window_rect.height() as i32 - height as i32, width, height);
+						f!(canvas, {draw_same_scale(canvas, replay_text, rect)});
+					}
+					
+					// On the results screen, the chat goes at the bottom.
+					if over && pause.is_none() {
+						let mut y = window_rect.height() as i32;
+						for chat_text in chat_texts.iter().rev() {
+							let (width, height) = get_texture_dim(chat_text);
+							y -= height as i32;
+							let rect = Rect::new(0, y, width, height);
+							f!(canvas, {draw_same_scale(canvas, chat_text, rect)});
+						}
+						if chatting {
+							f!(canvas, {darken(canvas, None)});
+							f!(canvas, {chat_prompt.draw(canvas)});
+						}
+					}
+					
 					if let Some(Pause{selection}) = pause {
 						f!(canvas, {darken(canvas, None)});
 						
@@ -0,0 +1,232 @@
+// A host without a window, which doesn't need SDL. It takes the connections,
+// relays everything that the clients send to each other, and simulates the
+// units like every other machine does, so that it can fix desyncs.
+//
+// tetris-server [address] [game mode] [rotation system]
+//
+// The game is started by typing start, once every player is ready, or by typing
+// force. There's also say, status, and quit.
+
+use std::{collections::VecDeque, env, io::{self, BufRead}, net::TcpListener, sync::mpsc, thread};
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+use itertools::izip;
+use tetris_core::State;
+use tetris_core::command::Command;
+use tetris_core::lenio::LenIO;
+use tetris_core::lockstep::{Desync, Timing};
+use tetris_core::network::{HandshakeEvent, NetworkCommand, NetworkEvent, NetworkPump, NetworkState};
+use tetris_core::room::{Room, RoomCommand};
+use tetris_core::rotation;
+use tetris_core::ui::GameModeSelection;
+use tetris_core::unit;
+
+const NAME: &str = "Server";
+const DEFAULT_ADDR: &str = "0.0.0.0:4141";
+const INPUT_DELAY: u64 = 2;
+
+fn print_status(room: &Room, state: &State, network_state: &NetworkState) {
+	let state = match state {
+		State::Play {over: true, ..} => "game over",
+		State::Play {..} => "playing",
+		_ => "lobby",
+	};
+	let names: Vec<_> = room.players.iter()
+		.map(|player|if player.ready {format!("{} (ready)", player.name)} else {player.name.clone()})
+		.collect();
+	println!(
+		"{} players, {} spectators, {}: {}",
+		names.len(), network_state.get_spectator_count(), state, names.join(", "));
+}
+
+fn main() {
+	let mut args = env::args().skip(1);
+	let addr = args.next().unwrap_or_else(||DEFAULT_ADDR.to_string());
+	let game_mode = args.next().map_or(GameModeSelection::default(), |name|
+		GameModeSelection::from_name(&name)
+			.expect("Unknown game mode, expected marathon, sprint, versus, game-of-life or finesse"));
+	let rotation_system = args.next().map_or(rotation::Kind::default(), |name|
+		rotation::Kind::from_name(&name)
+			.expect("Unknown rotation system, expected srs, ars or nrs"));
+	
+	let fps: u32 = 60;
+	let dpf: Duration = Duration::from_secs(1) / fps;
+	
+	let listener = TcpListener::bind(&addr)
+		.expect("Failed to bind address");
+	listener.set_nonblocking(true).unwrap();
+	let mut network_state = NetworkState::Host {
+		listener,
+		peers: Vec::new(),
+		pending: Vec::new(),
+	};
+	println!("Listening on {}", addr);
+	
+	let mut room = Room::new();
+	room.selected_game_mode = game_mode;
+	room.selected_rotation_system = rotation_system;
+	room.online = true;
+	room.input_delay = INPUT_DELAY;
+	room.timing = Timing {
+		frame_duration: dpf,
+		line_clear_duration: Duration::from_secs_f64(0.1),
+		game_of_life_duration: Duration::from_secs_f64(0.25),
+	};
+	let mut state = State::Lobby;
+	let mut commands = VecDeque::new();
+	
+	// Reading from stdin blocks, so it gets a thread of its own.
+	let (line_sender, line_receiver) = mpsc::channel();
+	thread::spawn(move||{
+		for line in io::stdin().lock().lines().map_while(Result::ok) {
+			if line_sender.send(line).is_err() {break}
+		}
+	});
+	
+	'running: loop {
+		let start = Instant::now();
+		
+		// @input
+		while let Ok(line) = line_receiver.try_recv() {
+			match line.trim() {
+				"start" | "force" if room.players.is_empty() => println!("There are no players yet"),
+				"start" if !room.is_ready() => println!("Not everyone is ready, type force to start anyway"),
+				"start" | "force" => commands.push_back(RoomCommand::StartGame(rand::random()).wrap()),
+				"status" => print_status(&room, &state, &network_state),
+				"quit" => break 'running,
+				"" => {}
+				line if line.starts_with("say ") => commands.push_back(RoomCommand::Chat {
+					sender: NAME.to_string(),
+					text: line["say ".len()..].trim().to_string(),
+				}.wrap()),
+				line => println!("Unknown command {}, expected start, force, say, status or quit", line),
+			}
+		}
+		
+		// @network
+		let mut network_command_pump = NetworkPump::new();
+		
+		while let Some(event) = network_command_pump.poll(&mut network_state) {
+			match event {
+				NetworkEvent::Command(NetworkCommand::RoomCommand(command)) =>
+				commands.push_back(command),
+				NetworkEvent::Command(NetworkCommand::Spectate) => {}
+				NetworkEvent::ClientLeft(addr) => {
+					println!("Connection to client lost");
+					for (index, player) in room.players.iter().enumerate().rev() {
+						if player.peer == Some(addr) {
+							commands.push_back(RoomCommand::DisconnectPlayer(index).wrap());
+						}
+					}
+				}
+				NetworkEvent::HostLeft => {}
+			}
+		}
+		
+		// Spectators can join in the middle of a game too.
+		if let NetworkState::Host {listener, pending, ..} = &mut network_state {
+			while let Ok(incoming) = listener.accept() {
+				incoming.0.set_nonblocking(true).unwrap();
+				pending.push((LenIO::new(incoming.0), incoming.1));
+				println!("Connection from {}", incoming.1);
+			}
+		}
+		
+		// @update
+		
+		// ROOM
+		while let Some(command) = commands.pop_front() {
+			command.execute(&mut network_state, |c|commands.push_back(c), (&mut room, &mut state));
+			if room.just_started {
+				println!("Game started");
+			}
+			if room.just_chatted {
+				if let Some((sender, text)) = room.chat.back() {
+					println!("{}: {}", sender, text);
+				}
+			}
+			if room.just_added_player || room.just_removed_player.is_some() || room.just_started {
+				print_status(&room, &state, &network_state);
+			}
+			room.reset_flags();
+		}
+		
+		// After the room commands, so that the room sent in Init has all of
+		// the commands that came in so far.
+		for event in network_state.poll_handshakes(NAME, &room) {
+			match event {
+				HandshakeEvent::Accepted(name) => println!("Connection to client {} established", name),
+				HandshakeEvent::Rejected(reason) => println!("Connection rejected: {}", reason),
+			}
+		}
+		
+		// UNITS
+		if let State::Play {over,players_lost,players_won,winner,..} = &mut state {
+			if !*over && !room.units.is_empty() {
+				let players = room.players.len() as u32;
+				match room.selected_game_mode {
+					GameModeSelection::Marathon | GameModeSelection::Sprint | GameModeSelection::GameOfLife | GameModeSelection::Finesse =>
+					if *players_won == players {*over = true}
+					GameModeSelection::Versus =>
+					if *players_lost == players-1 {
+						*over = true;
+						for (player, unit) in izip!(&room.players, &room.units) {
+							if let unit::State::Play = unit.base.state {
+								winner.get_or_insert(player.name.clone());
+							}
+						}
+					}
+				}
+				if *over {
+					match winner {
+						Some(winner) => println!("Game over, {} won", winner),
+						None => println!("Game over"),
+					}
+				}
+			}
+			
+			room.lockstep.advance(&mut room.units, &mut room.commands, &room.timing);
+			
+			for checksum in room.lockstep.new_checksums.drain(..) {
+				commands.push_back(RoomCommand::Checksum(checksum).wrap());
+			}
+			let mut desyncs: Vec<_> = room.lockstep.desyncs.drain(..).collect();
+			desyncs.sort();
+			desyncs.dedup_by_key(|desync|(desync.frame, desync.unit_id));
+			for Desync {frame, unit_id, local, remote} in desyncs {
+				println!(
+					"Desync on frame {} in unit {} ({}): checksum is {:016x} here, and {:016x} on the other side",
+					frame, unit_id, room.players[unit_id].name, local, remote);
+				if let Some(sync) = room.lockstep.get_sync(frame, unit_id) {
+					println!("Sending unit {} as it was on frame {}", unit_id, frame);
+					commands.push_back(RoomCommand::Sync(sync).wrap());
+				}
+			}
+			if room.lockstep.just_synced {
+				room.lockstep.just_synced = false;
+				*players_lost = room.units.iter().filter(|unit|matches!(unit.base.state, unit::State::Lose)).count() as u32;
+				*players_won = room.units.iter().filter(|unit|matches!(unit.base.state, unit::State::Win)).count() as u32;
+			}
+			
+			for (unit, player) in izip!(&mut room.units, &room.players) {
+				if unit.base.just_lost {*players_lost += 1}
+				if unit.base.just_won {*players_won += 1}
+				match room.selected_game_mode {
+					GameModeSelection::Marathon | GameModeSelection::Sprint =>
+					if unit.base.just_won {winner.get_or_insert(player.name.clone());}
+					_ => {}
+				}
+				unit.base.reset_flags();
+			}
+		}
+		
+		// TIMEKEEPING
+		let duration = start.elapsed();
+		let difference = match dpf.checked_sub(duration) {
+			Some(difference) => difference,
+			None => Duration::from_secs(0),
+		};
+		
+		sleep(difference);
+	}
+}
\ No newline at end of file
@@ -0,0 +1,31 @@
+use crate::vec2i;
+use serde::{Serialize,Deserialize};
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct Data {
+	texture_pos: vec2i,
+}
+impl Data {
+	pub const EMPTY: Data = Data{texture_pos:vec2i{x:0,y:0}};
+	pub const SHADOW: Data = Data{texture_pos:vec2i{x:1,y:0}};
+	pub const GRAY: Data = Data{texture_pos:vec2i{x:3,y:0}};
+	
+	pub const CYAN: Data = Data{texture_pos:vec2i{x:2,y:0}};
+	pub const ORANGE: Data = Data{texture_pos:vec2i{x:0,y:1}};
+	pub const BLUE: Data = Data{texture_pos:vec2i{x:1,y:1}};
+	pub const PINK: Data = Data{texture_pos:vec2i{x:2,y:1}};
+	pub const GREEN: Data = Data{texture_pos:vec2i{x:0,y:2}};
+	pub const PURPLE: Data = Data{texture_pos:vec2i{x:1,y:2}};
+	pub const YELLOW: Data = Data{texture_pos:vec2i{x:2,y:2}};
+	
+	pub const SENT_LINE: Data = Data{texture_pos:vec2i{x:3,y:1}};
+	pub const EMPTY_LINE: Data = Data{texture_pos:vec2i{x:3,y:2}};
+	
+	pub fn new(x: i32, y: i32) -> Self {
+		Self {
+			texture_pos: vec2i::new(x,y),
+		}
+	}
+	pub fn is_empty(&self) -> bool {*self == Data::EMPTY}
+	pub fn get_texture_pos(&self) -> vec2i {self.texture_pos}
+}
\ No newline at end of file
@@ -0,0 +1,258 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+use crate::game::{self, Well};
+use crate::ui::EnumSelect;
+use crate::unit::{self, UnitCommandKind};
+use enum_select_derive::EnumSelect;
+use serde::{Serialize,Deserialize};
+
+// How fast the bot plays, it's otherwise just as good on every difficulty.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, EnumSelect, Serialize, Deserialize)]
+pub enum Difficulty {
+	Easy,
+	#[default]
+	Medium,
+	Hard,
+}
+
+impl Difficulty {
+	pub fn from_name(name: &str) -> Option<Self> {
+		match name {
+			"easy" => Some(Difficulty::Easy),
+			"medium" => Some(Difficulty::Medium),
+			"hard" => Some(Difficulty::Hard),
+			_ => None,
+		}
+	}
+	// Frames between two of the bot's moves.
+	pub fn get_move_delay(&self) -> u32 {
+		match self {
+			Difficulty::Easy => 20,
+			Difficulty::Medium => 8,
+			Difficulty::Hard => 2,
+		}
+	}
+}
+
+// Weights for the well's features, from Yiyuan Lee's near perfect bot.
+const AGGREGATE_HEIGHT_WEIGHT: f64 = -0.510066;
+const LINES_WEIGHT: f64 = 0.760666;
+const HOLES_WEIGHT: f64 = -0.35663;
+const BUMPINESS_WEIGHT: f64 = -0.184483;
+
+// Plays a unit by itself. For every mino, it tries every rotation in every
+// column, picks the placement that leaves the best well behind, and then moves
+// the mino there one command at a time.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Bot {
+	pub difficulty: Difficulty,
+	plan: VecDeque<UnitCommandKind>,
+	move_countdown: u32,
+	fall_countdown: Duration,
+	// How many minos the unit had drawn when the last one was dropped. The
+	// bot waits for the next one, since its inputs take a while to go through.
+	dropped_at: Option<u64>,
+}
+
+impl Bot {
+	pub fn new(difficulty: Difficulty) -> Self {
+		Bot {
+			difficulty,
+			plan: VecDeque::new(),
+			move_countdown: 0,
+			fall_countdown: Duration::from_secs(0),
+			dropped_at: None,
+		}
+	}
+	pub fn append_commands(&mut self, queue: &mut Vec<UnitCommandKind>, base: &unit::Base, dpf: Duration) {
+		// The well might still have lines in it that are about to be cleared.
+		if !matches!(base.state, unit::State::Play) {return}
+		let draws = base.rng.get_draws();
+		if self.dropped_at == Some(draws) {return}
+		self.dropped_at = None;
+		
+		let falling_mino = match &base.falling_mino {
+			Some(falling_mino) => falling_mino,
+			None => return,
+		};
+		if self.plan.is_empty() {
+			self.plan = plan(falling_mino, base);
+			self.move_countdown = self.difficulty.get_move_delay();
+		}
+		
+		// MOVEMENT
+		
+		if self.move_countdown > 0 {
+			self.move_countdown -= 1;
+		}else if let Some(command) = self.plan.pop_front() {
+			if let UnitCommandKind::HardDrop = command {
+				self.dropped_at = Some(draws);
+				self.fall_countdown = Duration::from_secs(0);
+			}
+			queue.push(command);
+			self.move_countdown = self.difficulty.get_move_delay();
+		}
+		
+		// GRAVITY
+		
+		let fall_duration = base.get_fall_duration();
+		self.fall_countdown += dpf;
+		let mut g = 0;
+		if fall_duration.is_zero() {
+			g = i32::MAX;
+			self.fall_countdown = Duration::from_secs(0);
+		}else {
+			while self.fall_countdown >= fall_duration {
+				g += 1;
+				self.fall_countdown -= fall_duration;
+			}
+		}
+		if g != 0 && self.dropped_at.is_none() {
+			queue.push(UnitCommandKind::ApplyGravity(g));
+		}
+	}
+}
+
+// The commands that get the mino to the best placement.
+fn plan(falling_mino: &crate::Mino, base: &unit::Base) -> VecDeque<UnitCommandKind> {
+	let rotation_system = base.rotation_system.system();
+	let width = base.well.column_len() as i32;
+	let mut best: Option<(f64, VecDeque<UnitCommandKind>)> = None;
+	// Three turns to the right are just one to the left.
+	for rotations in [0, 1, 2, -1i32] {
+		for shift in -width..=width {
+			let mut mino = falling_mino.clone();
+			let mut commands = VecDeque::new();
+			
+			let rotated = (0..rotations.abs()).all(|_|if rotations < 0 {
+				game::try_rotl_mino(&mut mino, &base.well, rotation_system)
+			}else {
+				game::try_rotr_mino(&mut mino, &base.well, rotation_system)
+			});
+			let shifted = (0..shift.abs()).all(|_|if shift < 0 {
+				game::try_left_mino(&mut mino, &base.well)
+			}else {
+				game::try_right_mino(&mut mino, &base.well)
+			});
+			if !rotated || !shifted {continue}
+			
+			let mino = game::create_shadow_mino(&mino, &base.well);
+			// Garbage can push the well up into the mino.
+			if mino.blocks.iter().any(|block|block.y < 0) || game::check_mino_well_collision(&mino, &base.well) {continue}
+			let mut well = base.well.clone();
+			game::add_mino_to_well(&mino, &mut well);
+			let score = evaluate(&mut well);
+			
+			for _ in 0..rotations.abs() {
+				commands.push_back(if rotations < 0 {UnitCommandKind::RotateLeft} else {UnitCommandKind::RotateRight});
+			}
+			for _ in 0..shift.abs() {
+				commands.push_back(if shift < 0 {UnitCommandKind::MoveLeft} else {UnitCommandKind::MoveRight});
+			}
+			commands.push_back(UnitCommandKind::HardDrop);
+			
+			// The same placement can often be reached in more than one way.
+			let better = best.as_ref().is_none_or(|(best_score, best_commands)|
+				score > *best_score || score == *best_score && commands.len() < best_commands.len());
+			if better {
+				best = Some((score, commands));
+			}
+		}
+	}
+	best.map_or_else(||VecDeque::from(vec![UnitCommandKind::HardDrop]), |(_, commands)|commands)
+}
+
+// How good the well is, after the lines in it get cleared.
+fn evaluate(well: &mut Well) -> f64 {
+	let (width, height) = (well.column_len(), well.row_len());
+	let lines = (0..height)
+		.filter(|&y|(0..width).all(|x|!well[(x,y)].is_empty()))
+		.count();
+	game::try_clear_lines(well);
+	
+	let mut heights = Vec::with_capacity(width);
+	let mut holes = 0;
+	for x in 0..width {
+		let top = (0..height).find(|&y|!well[(x,y)].is_empty()).unwrap_or(height);
+		heights.push(height-top);
+		holes += (top..height).filter(|&y|well[(x,y)].is_empty()).count();
+	}
+	let aggregate_height: usize = heights.iter().sum();
+	let bumpiness: usize = heights.windows(2)
+		.map(|pair|pair[0].abs_diff(pair[1]))
+		.sum();
+	
+	AGGREGATE_HEIGHT_WEIGHT * aggregate_height as f64 +
+	LINES_WEIGHT * lines as f64 +
+	HOLES_WEIGHT * holes as f64 +
+	BUMPINESS_WEIGHT * bumpiness as f64
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{block, mino::Shape, unit::{Base, Settings}};
+	
+	fn create_base() -> Base {
+		Base::new(Settings::default(), game::MinoRng::new(game::Randomizer::default(), 0))
+	}
+	
+	fn fill_row(well: &mut Well, y: usize, gaps: std::ops::Range<usize>) {
+		for x in (0..well.column_len()).filter(|x|!gaps.contains(x)) {
+			well[(x,y)] = block::Data::GRAY;
+		}
+	}
+	
+	// Where the mino ends up after the commands.
+	fn follow(plan: &VecDeque<UnitCommandKind>, base: &Base) -> crate::Mino {
+		let rotation_system = base.rotation_system.system();
+		let mut mino = base.falling_mino.clone().unwrap();
+		for command in plan {
+			match command {
+				UnitCommandKind::MoveLeft => assert!(game::try_left_mino(&mut mino, &base.well)),
+				UnitCommandKind::MoveRight => assert!(game::try_right_mino(&mut mino, &base.well)),
+				UnitCommandKind::RotateLeft => assert!(game::try_rotl_mino(&mut mino, &base.well, rotation_system)),
+				UnitCommandKind::RotateRight => assert!(game::try_rotr_mino(&mut mino, &base.well, rotation_system)),
+				UnitCommandKind::HardDrop => mino = game::create_shadow_mino(&mino, &base.well),
+				_ => unreachable!(),
+			}
+		}
+		mino
+	}
+	
+	#[test]
+	fn i_piece_fills_the_gap_in_a_flat_well() {
+		let mut base = create_base();
+		let bottom = base.well.row_len()-1;
+		fill_row(&mut base.well, bottom, 0..4);
+		let mut mino = base.rotation_system.system().spawn_mino(Shape::I);
+		game::center_mino(&mut mino, &base.well_size);
+		base.set_falling_mino(mino);
+		
+		let plan = plan(base.falling_mino.as_ref().unwrap(), &base);
+		assert!(matches!(plan.back(), Some(UnitCommandKind::HardDrop)));
+		let mino = follow(&plan, &base);
+		let mut well = base.well.clone();
+		game::add_mino_to_well(&mino, &mut well);
+		game::try_clear_lines(&mut well);
+		assert!((0..well.column_len()).all(|x|well[(x,bottom)].is_empty()));
+	}
+	
+	#[test]
+	fn holes_and_height_make_a_well_worse() {
+		let well = create_base().well;
+		let bottom = well.row_len()-1;
+		
+		let mut flat = well.clone();
+		fill_row(&mut flat, bottom, 0..1);
+		let mut holed = well.clone();
+		fill_row(&mut holed, bottom-1, 0..1);
+		let mut cleared = well.clone();
+		fill_row(&mut cleared, bottom, 0..0);
+		
+		let flat_score = evaluate(&mut flat);
+		assert!(evaluate(&mut holed) < flat_score);
+		assert!(evaluate(&mut cleared) > flat_score);
+		assert!(cleared.elements_row_major_iter().all(|data|data.is_empty()));
+	}
+}
@@ -4,7 +4,9 @@ use serde::{Serialize, Deserialize};
 pub struct CommandWrapper<T> {
 	pub inner: T,
 	
-	#[serde(skip_serializing)]
+	// Commands that come in over the network aren't original, so they
+	// don't get sent out again.
+	#[serde(skip)]
 	pub original: bool,
 }
 impl<'a, T> CommandWrapper<T> {
@@ -0,0 +1,151 @@
+use std::collections::{HashSet, VecDeque};
+use crate::{Mino, vec2i};
+use crate::game::{self, Well, WellSize};
+use crate::rotation::RotationSystem;
+
+// Where the mino's blocks are, in an order that doesn't depend on the rotation
+// state that they came from.
+fn get_blocks(mino: &Mino) -> Vec<(i32, i32)> {
+	let mut blocks: Vec<_> = mino.blocks.iter().map(|block|(block.x, block.y)).collect();
+	blocks.sort_unstable();
+	blocks
+}
+
+// The fewest presses that get a new mino over to where this one locked, in
+// the well as it was before, the same way that the mino controller counts
+// them. Holding left or right until the mino hits something is one press.
+// There's None if a hard drop couldn't have put it there, like when it was
+// tucked under an overhang or kicked into a slot, as the presses don't tell
+// the whole story then.
+pub fn get_min_moves(mino: &Mino, well: &Well, well_size: &WellSize, rotation_system: &dyn RotationSystem) -> Option<u32> {
+	if mino.last_kick.is_some_and(|kick|kick != vec2i::ZERO) {
+		return None;
+	}
+	let target = get_blocks(mino);
+	
+	let mut spawned = rotation_system.spawn_mino(mino.shape);
+	game::center_mino(&mut spawned, well_size);
+	if game::check_mino_well_collision(&spawned, well) {
+		return None;
+	}
+	
+	let mut seen = HashSet::new();
+	let mut queue = VecDeque::new();
+	seen.insert((spawned.rotation.rem_euclid(4), get_blocks(&spawned)));
+	queue.push_back((spawned, 0));
+	while let Some((mino, moves)) = queue.pop_front() {
+		if get_blocks(&game::create_shadow_mino(&mino, well)) == target {
+			return Some(moves);
+		}
+		let moved = [
+			game::try_left_mino, game::try_right_mino,
+		].iter().flat_map(|try_move|{
+			let mut tapped = mino.clone();
+			let tapped = if try_move(&mut tapped, well) {Some(tapped)} else {None};
+			let mut held = mino.clone();
+			while try_move(&mut held, well) {}
+			[tapped, Some(held)]
+		}).chain([
+			game::try_rotl_mino, game::try_rotr_mino,
+		].iter().map(|try_rotate|{
+			let mut mino = mino.clone();
+			if try_rotate(&mut mino, well, rotation_system) {Some(mino)} else {None}
+		}));
+		for mino in moved.flatten() {
+			if seen.insert((mino.rotation.rem_euclid(4), get_blocks(&mino))) {
+				queue.push_back((mino, moves+1));
+			}
+		}
+	}
+	None
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{block, mino::Shape, rotation};
+	
+	// A mino that was hard dropped after the presses, as the unit would have
+	// it.
+	fn create_mino(shape: Shape, rotation: i32, x: i32, well: &Well) -> Mino {
+		let rotation_system = rotation::Srs;
+		let mut mino = rotation_system.spawn_mino(shape);
+		game::center_mino(&mut mino, &WellSize::default());
+		for _ in 0..rotation.rem_euclid(4) {
+			assert!(game::try_rotr_mino(&mut mino, well, &rotation_system));
+		}
+		let (lo, _) = mino.get_rect();
+		mino.translate(vec2i!(x-lo.x, 0));
+		while game::try_down_mino(&mut mino, well) {}
+		mino
+	}
+	
+	fn get_srs_min_moves(shape: Shape, rotation: i32, x: i32) -> Option<u32> {
+		let well = WellSize::default().new_well();
+		get_min_moves(&create_mino(shape, rotation, x, &well), &well, &WellSize::default(), &rotation::Srs)
+	}
+	
+	#[test]
+	fn o_piece() {
+		// It spawns in columns 4 and 5.
+		assert_eq!(get_srs_min_moves(Shape::O, 0, 4), Some(0));
+		assert_eq!(get_srs_min_moves(Shape::O, 0, 3), Some(1));
+		// Held to the wall.
+		assert_eq!(get_srs_min_moves(Shape::O, 0, 0), Some(1));
+		assert_eq!(get_srs_min_moves(Shape::O, 0, 8), Some(1));
+		// Two taps, or held to the wall and tapped back.
+		assert_eq!(get_srs_min_moves(Shape::O, 0, 2), Some(2));
+		assert_eq!(get_srs_min_moves(Shape::O, 0, 1), Some(2));
+	}
+	
+	#[test]
+	fn rotated_minos() {
+		assert_eq!(get_srs_min_moves(Shape::T, 0, 3), Some(0));
+		assert_eq!(get_srs_min_moves(Shape::T, 2, 3), Some(2));
+		assert_eq!(get_srs_min_moves(Shape::T, 1, 0), Some(2));
+		assert_eq!(get_srs_min_moves(Shape::I, 1, 0), Some(2));
+		assert_eq!(get_srs_min_moves(Shape::I, 1, 9), Some(2));
+	}
+	
+	#[test]
+	fn stack_stops_held_minos() {
+		let well_size = WellSize::default();
+		let mut well = well_size.new_well();
+		for y in 0..well.row_len() {
+			well[(0,y)] = block::Data::GRAY;
+		}
+		// Holding left stops next to the stack, rather than at the wall.
+		let mino = create_mino(Shape::O, 0, 1, &well);
+		assert_eq!(get_min_moves(&mino, &well, &well_size, &rotation::Srs), Some(1));
+	}
+	
+	#[test]
+	fn tucked_minos_have_no_min_moves() {
+		let well_size = WellSize::default();
+		let mut well = well_size.new_well();
+		let roof = well.row_len()-3;
+		for x in 0..3 {
+			well[(x,roof)] = block::Data::GRAY;
+		}
+		// Soft dropped next to the overhang, then slid under it.
+		let mut mino = create_mino(Shape::O, 0, 3, &well);
+		assert!(game::try_left_mino(&mut mino, &well));
+		assert!(game::try_left_mino(&mut mino, &well));
+		assert_eq!(get_min_moves(&mino, &well, &well_size, &rotation::Srs), None);
+		
+		// Dropped on top of the overhang instead.
+		let mino = create_mino(Shape::O, 0, 1, &well);
+		assert_eq!(get_min_moves(&mino, &well, &well_size, &rotation::Srs), Some(2));
+	}
+	
+	#[test]
+	fn kicked_minos_have_no_min_moves() {
+		let well = WellSize::default().new_well();
+		let mut mino = create_mino(Shape::T, 0, 3, &well);
+		mino.last_kick = Some(vec2i!(1,0));
+		assert_eq!(get_min_moves(&mino, &well, &WellSize::default(), &rotation::Srs), None);
+		// Rotating in place isn't a kick.
+		mino.last_kick = Some(vec2i::ZERO);
+		assert_eq!(get_min_moves(&mino, &well, &WellSize::default(), &rotation::Srs), Some(0));
+	}
+}
@@ -0,0 +1,627 @@
+use crate::block;
+use crate::Mino;
+use crate::mino::Shape;
+use crate::rotation::RotationSystem;
+use crate::vec2i;
+use rand::{Rng,SeedableRng,rngs::StdRng};
+use serde::{Serialize,Deserialize};
+use std::collections::VecDeque;
+use std::convert::TryFrom;
+
+pub type Well = array2d::Array2D<block::Data>;
+
+// Dimensions of the visible part of the well, and the number of hidden rows
+// above it, where minos spawn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WellSize {
+	pub width: usize,
+	pub height: usize,
+	pub buffer: usize,
+}
+
+impl Default for WellSize {
+	fn default() -> Self {
+		WellSize {width: 10, height: 20, buffer: 2}
+	}
+}
+
+impl WellSize {
+	// The I mino needs a width of at least 4 to spawn.
+	pub const MIN_LEN: usize = 4;
+	pub const MAX_LEN: usize = 100;
+	
+	// Makes a size that the game can be played with, as close to this one as
+	// it can be.
+	pub fn clamp(self) -> Self {
+		let width = self.width.clamp(Self::MIN_LEN, Self::MAX_LEN);
+		let height = self.height.clamp(Self::MIN_LEN, Self::MAX_LEN);
+		WellSize {width, height, buffer: self.buffer.min(height)}
+	}
+	pub fn new_well(&self) -> Well {
+		Well::filled_with(block::Data::EMPTY, self.width, self.height + self.buffer)
+	}
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Randomizer {
+	// Every mino is equally likely, every time.
+	Hard,
+	// Deals out shuffled bags with one, or two, of every mino.
+	#[default]
+	Fair, Bag14,
+	// TGM2, tries up to 6 times to get a mino that isn't one of the last 4.
+	TgmHistory,
+	// TGM3, like TgmHistory, but draws from a pool of 35 minos, which fills up
+	// with the minos that haven't been seen in the longest time.
+	Tgm3,
+	// NES, rerolls once if it picked the same mino as last time.
+	Nes,
+}
+
+impl Randomizer {
+	pub fn from_name(name: &str) -> Option<Self> {
+		match name {
+			"hard" => Some(Randomizer::Hard),
+			"7-bag" => Some(Randomizer::Fair),
+			"14-bag" => Some(Randomizer::Bag14),
+			"tgm" => Some(Randomizer::TgmHistory),
+			"tgm3" => Some(Randomizer::Tgm3),
+			"nes" => Some(Randomizer::Nes),
+			_ => None,
+		}
+	}
+}
+
+// Tries, counting the first one.
+const TGM_ROLLS: usize = 6;
+// The TGM randomizers never start with a mino that could force an overhang.
+const TGM_FIRST_SHAPES: [Shape; 4] = [Shape::I, Shape::J, Shape::L, Shape::T];
+
+// Only the seed and the number of minos generated so far get serialized, the
+// rest of the state is rebuilt by generating that many minos again. StdRng is
+// used because, unlike SmallRng, it's the same on every platform.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(try_from = "MinoRngState", into = "MinoRngState")]
+pub struct MinoRng {
+	randomizer: Randomizer,
+	seed: u64,
+	draws: u64,
+	rng: StdRng,
+	// The bag, or the TGM3 pool.
+	stack: Vec<Shape>,
+	history: VecDeque<Shape>,
+	// TGM3 only, from the longest unseen mino to the most recently seen one.
+	order: Vec<Shape>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct MinoRngState {
+	randomizer: Randomizer,
+	seed: u64,
+	draws: u64,
+}
+
+// Getting back to where a MinoRng was means drawing all of its minos again,
+// so anything sent with more draws than any game gets to is turned away.
+const MAX_DRAWS: u64 = 1 << 20;
+
+impl TryFrom<MinoRngState> for MinoRng {
+	type Error = String;
+	fn try_from(state: MinoRngState) -> Result<Self, String> {
+		if state.draws > MAX_DRAWS {
+			return Err(format!("Too many draws: {}", state.draws));
+		}
+		let mut mino_rng = MinoRng::new(state.randomizer, state.seed);
+		for _ in 0..state.draws {
+			mino_rng.generate();
+		}
+		Ok(mino_rng)
+	}
+}
+
+impl From<MinoRng> for MinoRngState {
+	fn from(mino_rng: MinoRng) -> Self {
+		let MinoRng {randomizer, seed, draws, ..} = mino_rng;
+		MinoRngState {randomizer, seed, draws}
+	}
+}
+
+impl MinoRng {
+	pub fn new(randomizer: Randomizer, seed: u64) -> MinoRng {
+		use Shape::*;
+		let (stack, history, order) = match randomizer {
+			Randomizer::TgmHistory => (Vec::new(), vec![Z, S, Z, S], Vec::new()),
+			Randomizer::Tgm3 => (Shape::ALL.iter().cycle().take(35).copied().collect(), vec![S, Z, S, Z], Shape::ALL.to_vec()),
+			_ => (Vec::new(), Vec::new(), Vec::new()),
+		};
+		MinoRng {
+			randomizer, seed, draws: 0,
+			rng: StdRng::seed_from_u64(seed),
+			stack,
+			history: history.into(),
+			order,
+		}
+	}
+	pub fn get_seed(&self) -> u64 {
+		self.seed
+	}
+	pub fn get_draws(&self) -> u64 {
+		self.draws
+	}
+	pub fn generate(&mut self) -> Shape {
+		let first = self.draws == 0;
+		self.draws += 1;
+		let MinoRng {rng, stack, history, order, ..} = self;
+		let shape = match self.randomizer {
+			Randomizer::Hard => {
+				Shape::ALL[rng.gen_range(0..7)]
+			}
+			Randomizer::Fair | Randomizer::Bag14 => {
+				if stack.is_empty() {
+					let copies = if let Randomizer::Fair = self.randomizer {1} else {2};
+					for _ in 0..copies {
+						stack.extend_from_slice(&Shape::ALL);
+					}
+					for i in 0..stack.len()-1 {
+						let j = i + rng.gen_range(0..stack.len()-i);
+						stack.swap(i, j);
+					}
+				}
+				stack.pop().unwrap()
+			}
+			Randomizer::TgmHistory => {
+				let mut shape = Shape::ALL[rng.gen_range(0..7)];
+				if first {
+					shape = TGM_FIRST_SHAPES[rng.gen_range(0..4)];
+				}else {
+					for _ in 1..TGM_ROLLS {
+						if !history.contains(&shape) {break}
+						shape = Shape::ALL[rng.gen_range(0..7)];
+					}
+				}
+				history.pop_front();
+				history.push_back(shape);
+				shape
+			}
+			Randomizer::Tgm3 => {
+				let shape;
+				if first {
+					shape = TGM_FIRST_SHAPES[rng.gen_range(0..4)];
+					order.retain(|s|*s != shape);
+					order.push(shape);
+				}else {
+					let mut i = rng.gen_range(0..stack.len());
+					for _ in 1..TGM_ROLLS {
+						if !history.contains(&stack[i]) {break}
+						// Every failed roll puts the most droughted mino in the pool.
+						stack[i] = order[0];
+						i = rng.gen_range(0..stack.len());
+					}
+					shape = stack[i];
+					order.retain(|s|*s != shape);
+					order.push(shape);
+					stack[i] = order[0];
+				}
+				history.pop_front();
+				history.push_back(shape);
+				shape
+			}
+			Randomizer::Nes => {
+				let mut i = rng.gen_range(0..8);
+				if i == 7 || history.back() == Some(&Shape::ALL[i]) {
+					i = rng.gen_range(0..7);
+				}
+				let shape = Shape::ALL[i];
+				history.clear();
+				history.push_back(shape);
+				shape
+			}
+		};
+		shape
+	}
+}
+
+// Picks the gaps in garbage lines. It's splitmix64, which is small enough to
+// be serialized as is, and comes out the same on every machine.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GarbageRng {
+	state: u64,
+}
+
+impl GarbageRng {
+	pub fn new(seed: u64) -> Self {
+		GarbageRng {state: seed}
+	}
+	pub fn get_gap(&mut self, width: usize) -> usize {
+		self.state = self.state.wrapping_add(0x9e3779b97f4a7c15);
+		let mut z = self.state;
+		z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+		z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+		z ^= z >> 31;
+		(z % width as u64) as usize
+	}
+}
+
+pub fn check_block_in_bounds(block: &vec2i, dim: &vec2i) -> bool {
+	block.x >= 0 && block.x < dim.x && block.y < dim.y
+}
+
+pub fn check_block_well_collision(block: &vec2i, well: &Well) -> bool {
+	let dim = vec2i::from((well.column_len(),well.row_len()));
+	!check_block_in_bounds(block, &dim) ||
+	(block.y >= 0 && well[(block.x as usize, block.y as usize)] != block::Data::EMPTY)
+}
+
+pub fn check_mino_well_collision(mino: &Mino, well: &Well) -> bool {
+	mino.blocks.iter().any(|block|check_block_well_collision(block, well))
+}
+
+pub fn may_mutate_mino<F>(mino: &Mino, well: &Well, f: F) -> bool
+where F: Fn(&mut Mino) {
+	let mut mutated_mino = mino.clone();
+	f(&mut mutated_mino);
+	!check_mino_well_collision(&mutated_mino, well)
+}
+pub fn may_down_mino(mino: &Mino, well: &Well) -> bool {
+	may_mutate_mino(mino, well, |mino|mino.down())
+}
+
+pub fn try_mutate_mino<F>(mino: &mut Mino, well: &Well, f: F) -> bool
+where F: Fn(&mut Mino, &Well) {
+	let mut mutated_mino = mino.clone();
+	f(&mut mutated_mino, well);
+	if !check_mino_well_collision(&mutated_mino, well) {
+		*mino = mutated_mino;
+		return true;
+	}
+	false
+}
+pub fn try_left_mino(mino: &mut Mino, well: &Well) -> bool{
+	try_mutate_mino(mino, well, |mino,_|mino.left())
+}
+pub fn try_right_mino(mino: &mut Mino, well: &Well) -> bool{
+	try_mutate_mino(mino, well, |mino,_|mino.right())
+}
+pub fn try_down_mino(mino: &mut Mino, well: &Well) -> bool{
+	try_mutate_mino(mino, well, |mino,_|mino.down())
+}
+
+pub fn create_shadow_mino(mino: &Mino, well: &Well) -> Mino {
+	let mut shadow_mino = mino.clone();
+	shadow_mino.make_shadow();
+	while try_down_mino(&mut shadow_mino, well) {}
+	shadow_mino
+}
+
+pub fn try_clear_lines(well: &mut Well) {
+	let mut dy: usize = 0;
+	for y in (0..well.row_len()).rev() {
+		let mut count = 0;
+		for x in 0..well.column_len() {
+			count += (well[(x,y)] != block::Data::EMPTY) as usize;
+			if dy != 0 {
+				well[(x,y+dy)] = well[(x,y)];
+				well[(x,y)] = block::Data::EMPTY;
+			}
+		}
+		if count == well.column_len() {
+			dy += 1;
+		}
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TSpin {Mini, Full}
+
+// Uses the 3-corner rule: the last move must have been a rotation, and at
+// least 3 of the cells diagonal to the T's center must be occupied. It's a
+// mini T-spin if only one of the corners the T points at is occupied, unless
+// the rotation used one of the long (1 by 2) kicks.
+pub fn check_t_spin(mino: &Mino, well: &Well) -> Option<TSpin> {
+	if mino.shape != Shape::T {return None}
+	let kick = mino.last_kick?;
+	
+	let is_neighbor = |a: &vec2i, b: &vec2i|{let d = *a-*b; d.x.abs()+d.y.abs() == 1};
+	let center = *mino.blocks.iter()
+		.find(|a|mino.blocks.iter().filter(|b|is_neighbor(a,b)).count() == 3)?;
+	let flat = [vec2i::UP, vec2i::RIGHT, vec2i::DOWN, vec2i::LEFT].iter()
+		.find(|dir|!mino.blocks.contains(&(center+**dir)))?;
+	let front = -*flat;
+	let side = front.rot90r();
+	
+	let count_occupied = |corners: [vec2i; 2]|
+		corners.iter().filter(|corner|check_block_well_collision(corner, well)).count();
+	let front_corners = count_occupied([center+front+side, center+front-side]);
+	let back_corners = count_occupied([center-front+side, center-front-side]);
+	
+	if front_corners+back_corners < 3 {None}
+	else if front_corners == 2 || (kick.x.abs() == 1 && kick.y.abs() == 2) {Some(TSpin::Full)}
+	else {Some(TSpin::Mini)}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TopOut {BlockOut, LockOut, PartialLockOut}
+
+// Which top out rules are checked. With a rule turned off, the game goes on
+// through it: a mino that spawned on top of the stack locks over it, and the
+// blocks that lock above the hidden rows are cut off.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TopOutRules {
+	pub block_out: bool,
+	pub lock_out: bool,
+	pub partial_lock_out: bool,
+}
+
+impl Default for TopOutRules {
+	fn default() -> Self {
+		TopOutRules {block_out: true, lock_out: true, partial_lock_out: false}
+	}
+}
+
+// Checks a mino that just spawned.
+pub fn check_block_out(mino: &Mino, well: &Well, rules: &TopOutRules) -> Option<TopOut> {
+	if rules.block_out && check_mino_well_collision(mino, well) {Some(TopOut::BlockOut)}
+	else {None}
+}
+
+// Checks a mino that is about to lock.
+pub fn check_lock_out(mino: &Mino, well: &Well, well_size: &WellSize, rules: &TopOutRules) -> Option<TopOut> {
+	let hidden = mino.blocks.iter().filter(|block|block.y < well_size.buffer as i32).count();
+	if rules.lock_out && hidden == mino.blocks.len() {Some(TopOut::LockOut)}
+	else if rules.partial_lock_out && hidden > 0 {Some(TopOut::PartialLockOut)}
+	else if rules.block_out && !mino_fits_in_well(mino, well) {Some(TopOut::BlockOut)}
+	else {None}
+}
+
+pub fn mino_fits_in_well(mino: &Mino, well: &Well) -> bool {
+	for block in mino.blocks.iter() {
+		if block.y < 0 || well[(block.x as usize, block.y as usize)] != block::Data::EMPTY {
+			return false;
+		}
+	}
+	true
+}
+
+// Like add_mino_to_well, but for minos that the top out rules let through.
+pub fn force_mino_into_well(mino: &Mino, well: &mut Well) {
+	for (block, data) in mino.blocks.iter().zip(mino.blocks_data.iter()) {
+		if block.y >= 0 {
+			well[(block.x as usize, block.y as usize)] = *data;
+		}
+	}
+}
+
+pub fn add_mino_to_well(mino: &Mino, well: &mut Well) {
+	for (block, data) in mino.blocks.iter().zip(mino.blocks_data.iter()) {
+		assert!(block.y >= 0 && well[(block.x as usize, block.y as usize)] == block::Data::EMPTY);
+		well[(block.x as usize, block.y as usize)] = *data;
+	}
+}
+
+// Places the mino in the middle of the well, right above the visible part.
+pub fn center_mino(mino: &mut Mino, well_size: &WellSize) {
+	let ext = mino.get_size();
+	mino.translate(vec2i::RIGHT * (well_size.width as i32-ext.x)/2);
+	mino.translate(vec2i::DOWN * (well_size.buffer as i32-ext.y).max(0));
+}
+
+pub fn reset_mino(mino: &mut Mino, rotation_system: &dyn RotationSystem) {
+	*mino = rotation_system.spawn_mino(mino.shape);
+}
+
+fn try_kick_mino(mino: &mut Mino, well: &Well, rotation_system: &dyn RotationSystem, rotation: i32) -> bool {
+	let mut rotated_mino = mino.clone();
+	rotation_system.rotate_mino(&mut rotated_mino, rotation);
+	for kick in rotation_system.get_kicks(mino, &rotated_mino, well) {
+		let mut kicked_mino = rotated_mino.clone();
+		kicked_mino.translate(*kick);
+		kicked_mino.last_kick = Some(*kick);
+		if !check_mino_well_collision(&kicked_mino, well) {
+			*mino = kicked_mino;
+			return true;
+		}
+	}
+	false
+}
+pub fn try_rotl_mino(mino: &mut Mino, well: &Well, rotation_system: &dyn RotationSystem) -> bool{
+	try_kick_mino(mino, well, rotation_system, -1)
+}
+pub fn try_rotr_mino(mino: &mut Mino, well: &Well, rotation_system: &dyn RotationSystem) -> bool{
+	try_kick_mino(mino, well, rotation_system, 1)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::rotation::{self, RotationSystem};
+	
+	const NO_RULES: TopOutRules = TopOutRules {block_out: false, lock_out: false, partial_lock_out: false};
+	const ALL_RULES: TopOutRules = TopOutRules {block_out: true, lock_out: true, partial_lock_out: true};
+	
+	fn create_mino(shape: Shape, pos: vec2i) -> Mino {
+		let mut mino = rotation::Srs.spawn_mino(shape);
+		mino.translate(pos);
+		mino
+	}
+	
+	const RANDOMIZERS: [Randomizer; 6] = [
+		Randomizer::Hard, Randomizer::Fair, Randomizer::Bag14,
+		Randomizer::TgmHistory, Randomizer::Tgm3, Randomizer::Nes,
+	];
+	
+	fn generate(mino_rng: &mut MinoRng, len: usize) -> Vec<Shape> {
+		(0..len).map(|_|mino_rng.generate()).collect()
+	}
+	
+	#[test]
+	fn well_sizes_are_clamped_to_playable_ones() {
+		assert_eq!(WellSize::default().clamp(), WellSize::default());
+		let WellSize {width, height, buffer} = WellSize {width: 0, height: 2, buffer: 10}.clamp();
+		assert_eq!((width, height, buffer), (4, 4, 4));
+		let WellSize {width, height, ..} = WellSize {width: usize::MAX, height: 1000, buffer: 2}.clamp();
+		assert_eq!((width, height), (WellSize::MAX_LEN, WellSize::MAX_LEN));
+	}
+	
+	#[test]
+	fn same_seed_same_minos() {
+		for randomizer in RANDOMIZERS {
+			let minos = generate(&mut MinoRng::new(randomizer, 42), 1000);
+			assert_eq!(generate(&mut MinoRng::new(randomizer, 42), 1000), minos, "{:?}", randomizer);
+			assert_ne!(generate(&mut MinoRng::new(randomizer, 43), 1000), minos, "{:?}", randomizer);
+		}
+	}
+	
+	#[test]
+	fn deserialized_mino_rng_carries_on() {
+		for randomizer in RANDOMIZERS {
+			let mut mino_rng = MinoRng::new(randomizer, 7);
+			generate(&mut mino_rng, 123);
+			let bytes = bincode::serialize(&mino_rng).unwrap();
+			let mut deserialized: MinoRng = bincode::deserialize(&bytes).unwrap();
+			assert_eq!(deserialized.get_seed(), 7);
+			assert_eq!(deserialized.get_draws(), 123);
+			assert_eq!(generate(&mut deserialized, 1000), generate(&mut mino_rng, 1000), "{:?}", randomizer);
+		}
+	}
+	
+	#[test]
+	fn mino_rng_with_too_many_draws_is_rejected() {
+		let state = MinoRngState {randomizer: Randomizer::Fair, seed: 0, draws: u64::MAX};
+		let bytes = bincode::serialize(&state).unwrap();
+		assert!(bincode::deserialize::<MinoRng>(&bytes).is_err());
+	}
+	
+	#[test]
+	fn lock_out_rules() {
+		let well_size = WellSize::default();
+		let well = well_size.new_well();
+		// Two rows tall, so it's in the hidden rows, halfway out, or visible.
+		for (y, top_out) in [(-1, Some(TopOut::LockOut)), (0, Some(TopOut::LockOut)), (1, Some(TopOut::PartialLockOut)), (2, None)] {
+			let mino = create_mino(Shape::O, vec2i!(4,y));
+			assert_eq!(check_lock_out(&mino, &well, &well_size, &ALL_RULES), top_out);
+			assert_eq!(check_lock_out(&mino, &well, &well_size, &NO_RULES), None);
+		}
+		
+		let rules = TopOutRules {lock_out: false, ..ALL_RULES};
+		let mino = create_mino(Shape::O, vec2i!(4,0));
+		assert_eq!(check_lock_out(&mino, &well, &well_size, &rules), Some(TopOut::PartialLockOut));
+		let rules = TopOutRules {partial_lock_out: false, ..ALL_RULES};
+		let mino = create_mino(Shape::O, vec2i!(4,1));
+		assert_eq!(check_lock_out(&mino, &well, &well_size, &rules), None);
+	}
+	
+	#[test]
+	fn block_out_rule() {
+		let well_size = WellSize::default();
+		let mut well = well_size.new_well();
+		well[(4,1)] = block::Data::GRAY;
+		let mino = create_mino(Shape::O, vec2i!(4,0));
+		assert_eq!(check_block_out(&mino, &well, &ALL_RULES), Some(TopOut::BlockOut));
+		assert_eq!(check_block_out(&mino, &well, &NO_RULES), None);
+		
+		let mino = create_mino(Shape::O, vec2i!(4,10));
+		well[(4,11)] = block::Data::GRAY;
+		assert_eq!(check_lock_out(&mino, &well, &well_size, &ALL_RULES), Some(TopOut::BlockOut));
+		assert_eq!(check_lock_out(&mino, &well, &well_size, &NO_RULES), None);
+	}
+	
+	#[test]
+	fn forced_minos_are_cut_off_above_the_well() {
+		let well_size = WellSize::default();
+		let mut well = well_size.new_well();
+		let mino = create_mino(Shape::O, vec2i!(4,-1));
+		force_mino_into_well(&mino, &mut well);
+		let blocks = well.elements_row_major_iter().filter(|block|!block.is_empty()).count();
+		assert_eq!(blocks, 2);
+		assert!(!well[(4,0)].is_empty() && !well[(5,0)].is_empty());
+	}
+	
+	// The longest run of minos without a given one, for every mino.
+	fn get_drought(minos: &[Shape]) -> usize {
+		let mut last_seen = [0; 7];
+		let mut drought = 0;
+		for (i, mino) in minos.iter().enumerate() {
+			let shape = Shape::ALL.iter().position(|shape|shape == mino).unwrap();
+			drought = drought.max(i - last_seen[shape]);
+			last_seen[shape] = i;
+		}
+		drought
+	}
+	
+	// How many of the minos are the same as one of the 4 before them.
+	fn get_repeats(minos: &[Shape]) -> usize {
+		minos.windows(5).filter(|window|window[..4].contains(&window[4])).count()
+	}
+	
+	#[test]
+	fn every_mino_is_equally_likely() {
+		for randomizer in RANDOMIZERS {
+			let minos = generate(&mut MinoRng::new(randomizer, 1), 70_000);
+			for shape in Shape::ALL {
+				let count = minos.iter().filter(|mino|**mino == shape).count();
+				assert!((9_600..=10_400).contains(&count), "{:?} dealt {} {:?}", randomizer, count, shape);
+			}
+		}
+	}
+	
+	#[test]
+	fn bags_keep_droughts_short() {
+		let minos = generate(&mut MinoRng::new(Randomizer::Fair, 1), 70_000);
+		assert!(get_drought(&minos) <= 13);
+		for bag in minos.chunks(7) {
+			assert!(Shape::ALL.iter().all(|shape|bag.contains(shape)));
+		}
+		
+		let minos = generate(&mut MinoRng::new(Randomizer::Bag14, 1), 70_000);
+		assert!(get_drought(&minos) <= 25);
+		for bag in minos.chunks(14) {
+			assert!(Shape::ALL.iter().all(|shape|bag.iter().filter(|mino|*mino == shape).count() == 2));
+		}
+	}
+	
+	#[test]
+	fn tgm_randomizers_avoid_repeats_and_droughts() {
+		let hard = generate(&mut MinoRng::new(Randomizer::Hard, 1), 70_000);
+		let tgm = generate(&mut MinoRng::new(Randomizer::TgmHistory, 1), 70_000);
+		let tgm3 = generate(&mut MinoRng::new(Randomizer::Tgm3, 1), 70_000);
+		assert!(get_repeats(&hard) > 30_000);
+		assert!(get_repeats(&tgm) < 3_500);
+		assert!(get_repeats(&tgm3) < 3_500);
+		assert!(get_drought(&hard) > 60);
+		assert!(get_drought(&tgm) < 45);
+		assert!(get_drought(&tgm3) < 25);
+		
+		// With 4 different minos in the history, all 6 tries hit one of them
+		// with a chance of (4/7)^6, about 3.5%. It'd be 6% with 5 tries, and
+		// 2% with 7.
+		let (repeats, windows) = tgm.windows(5)
+			.filter(|window|(1..4).all(|i|!window[..i].contains(&window[i])))
+			.fold((0, 0), |(repeats, windows), window|(repeats + window[..4].contains(&window[4]) as usize, windows + 1));
+		let rate = repeats as f64 / windows as f64;
+		assert!((0.03..0.04).contains(&rate), "{}", rate);
+		
+		for seed in 0..100 {
+			for randomizer in [Randomizer::TgmHistory, Randomizer::Tgm3] {
+				assert!(TGM_FIRST_SHAPES.contains(&MinoRng::new(randomizer, seed).generate()));
+			}
+		}
+	}
+	
+	#[test]
+	fn tgm3_refills_the_pool_with_the_most_droughted_mino() {
+		let mut mino_rng = MinoRng::new(Randomizer::Tgm3, 1);
+		let mut last_seen: Vec<Option<usize>> = vec![None; 7];
+		for i in 0..1000 {
+			let shape = mino_rng.generate();
+			last_seen[Shape::ALL.iter().position(|s|*s == shape).unwrap()] = Some(i);
+			
+			assert_eq!(mino_rng.stack.len(), 35);
+			let mut order = Shape::ALL.to_vec();
+			order.sort_by_key(|shape|last_seen[Shape::ALL.iter().position(|s|s == shape).unwrap()]);
+			assert_eq!(mino_rng.order, order);
+			if i > 0 {
+				assert!(mino_rng.stack.contains(&order[0]));
+			}
+		}
+	}
+}
@@ -0,0 +1,108 @@
+use std::time::Duration;
+use serde::{Serialize,Deserialize};
+
+// Gravity is given as the time it takes a mino to fall one row. A duration of
+// zero means 20G, where minos fall straight to the ground.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Kind {#[default] Classic, Worldwide, Tgm, Instant}
+
+// Internal gravity from TGM, in 1/256ths of a row per frame, and the TGM
+// level from which it applies. It goes by TGM's own level, which the units
+// keep track of along with the usual one.
+const TGM_GRAVITY: [(u32, u32); 30] = [
+	(0, 4), (30, 6), (35, 8), (40, 10), (50, 12), (60, 16), (70, 32), (80, 48),
+	(90, 64), (100, 80), (120, 96), (140, 112), (160, 128), (170, 144),
+	(200, 4), (220, 32), (230, 64), (233, 96), (236, 128), (239, 160),
+	(243, 192), (247, 224), (251, 256), (300, 512), (330, 768), (360, 1024),
+	(400, 1280), (420, 1024), (450, 768), (500, 5120),
+];
+const TGM_20G: u32 = 5120;
+
+impl Kind {
+	pub fn from_name(name: &str) -> Option<Self> {
+		match name {
+			"classic" => Some(Kind::Classic),
+			"worldwide" => Some(Kind::Worldwide),
+			"tgm" => Some(Kind::Tgm),
+			"20g" => Some(Kind::Instant),
+			_ => None,
+		}
+	}
+	
+	// The level is TGM's level for Tgm, and the unit's level otherwise.
+	pub fn get_fall_duration(&self, level: u32) -> Duration {
+		match self {
+			Kind::Classic => {
+				let level = level.max(1);
+				let base: Duration = Duration::from_secs_f64(0.40);
+				let level = (level-1) as f64;
+				base.div_f64(1f64 + level * 0.15)
+			}
+			Kind::Worldwide => {
+				// The curve stops making sense after level 20, which is already
+				// faster than 20G.
+				let level = (level.clamp(1, 20)-1) as f64;
+				Duration::from_secs_f64((0.8 - level*0.007).powf(level))
+			}
+			Kind::Tgm => {
+				let (_, gravity) = TGM_GRAVITY.iter()
+					.rev()
+					.find(|(from, _)|*from <= level)
+					.unwrap();
+				if *gravity >= TGM_20G {
+					Duration::from_secs(0)
+				}else {
+					Duration::from_secs(1) * 256 / 60 / *gravity
+				}
+			}
+			Kind::Instant => Duration::from_secs(0),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	
+	fn assert_secs(duration: Duration, secs: f64) {
+		assert!((duration.as_secs_f64() - secs).abs() < 0.000_01, "{:?} isn't {}s", duration, secs);
+	}
+	
+	#[test]
+	fn worldwide_matches_the_guideline_table() {
+		let table = [
+			1.0, 0.793, 0.6178, 0.47273, 0.3552, 0.262, 0.18968, 0.13473,
+			0.09388, 0.06415, 0.04298, 0.02822, 0.01815, 0.01144, 0.00706,
+		];
+		for (level, secs) in (1..).zip(table.iter()) {
+			assert_secs(Kind::Worldwide.get_fall_duration(level), *secs);
+		}
+		assert_eq!(Kind::Worldwide.get_fall_duration(30), Kind::Worldwide.get_fall_duration(20));
+	}
+	
+	#[test]
+	fn tgm_matches_the_internal_gravity_table() {
+		// Frames it takes to fall one row, at 60 frames per second.
+		let frames = |level|Kind::Tgm.get_fall_duration(level).as_secs_f64() * 60.0;
+		let assert_frames = |level, expected: f64|
+			assert!((frames(level) - expected).abs() < 0.000_01, "level {} takes {} frames", level, frames(level));
+		assert_frames(0, 64.0);
+		assert_frames(29, 64.0);
+		assert_frames(30, 256.0 / 6.0);
+		assert_frames(100, 256.0 / 80.0);
+		assert_frames(199, 256.0 / 144.0);
+		assert_frames(200, 64.0);
+		assert_frames(251, 1.0);
+		assert_frames(300, 0.5);
+		assert_frames(499, 256.0 / 768.0);
+		assert!(Kind::Tgm.get_fall_duration(500).is_zero());
+		assert!(Kind::Tgm.get_fall_duration(999).is_zero());
+	}
+	
+	#[test]
+	fn classic_and_instant() {
+		assert_secs(Kind::Classic.get_fall_duration(1), 0.4);
+		assert_secs(Kind::Classic.get_fall_duration(11), 0.16);
+		assert!(Kind::Instant.get_fall_duration(1).is_zero());
+	}
+}
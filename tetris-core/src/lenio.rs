@@ -0,0 +1,188 @@
+#![allow(dead_code)]
+use std::io::{Result,Read,Write,Error,ErrorKind};
+
+// Every frame starts with its length, as a big endian u32.
+const HEADER_LENGTH: usize = 4;
+const MAX_LENGTH: usize = 1 << 24;
+const CHUNK_LENGTH: usize = 4096;
+
+// Splits a stream into length prefixed frames. Reads and writes can be partial,
+// which happens all the time with non-blocking sockets, so both directions
+// keep a buffer of the bytes that are still in flight.
+#[derive(Debug)]
+pub struct LenIO<T> {
+	inner: T,
+	read_buf: Vec<u8>,
+	// Length of the frame that was returned by the last read, which gets
+	// dropped from read_buf on the next one.
+	read_done: usize,
+	write_buf: Vec<u8>,
+}
+
+impl<T> LenIO<T> {
+	pub fn new(inner: T) -> Self {
+		Self {
+			inner,
+			read_buf: Vec::with_capacity(CHUNK_LENGTH),
+			read_done: 0,
+			write_buf: Vec::new(),
+		}
+	}
+	pub fn get_ref(&self) -> &T {
+		&self.inner
+	}
+	fn get_frame_length(&self) -> Result<Option<usize>> {
+		if self.read_buf.len() < HEADER_LENGTH {
+			return Ok(None);
+		}
+		let mut header = [0; HEADER_LENGTH];
+		header.copy_from_slice(&self.read_buf[..HEADER_LENGTH]);
+		let len = u32::from_be_bytes(header) as usize;
+		if len > MAX_LENGTH {
+			return Err(Error::new(ErrorKind::InvalidData, "Frame is too long"));
+		}
+		Ok(Some(len))
+	}
+}
+
+impl<T: Read> LenIO<T> {
+	// Returns the next whole frame. If it hasn't fully arrived yet, returns the
+	// error from the inner reader (WouldBlock for non-blocking sockets), and
+	// keeps what it got so far for the next call.
+	pub fn read(&mut self) -> Result<&[u8]> {
+		self.read_buf.drain(..self.read_done);
+		self.read_done = 0;
+		
+		loop {
+			if let Some(len) = self.get_frame_length()? {
+				if self.read_buf.len() >= HEADER_LENGTH + len {
+					self.read_done = HEADER_LENGTH + len;
+					return Ok(&self.read_buf[HEADER_LENGTH..self.read_done]);
+				}
+			}
+			
+			let mut chunk = [0; CHUNK_LENGTH];
+			match self.inner.read(&mut chunk) {
+				Ok(0) => return Err(Error::new(ErrorKind::UnexpectedEof, "Connection was closed")),
+				Ok(bytes) => self.read_buf.extend_from_slice(&chunk[..bytes]),
+				Err(err) if err.kind() == ErrorKind::Interrupted => {}
+				Err(err) => return Err(err),
+			}
+		}
+	}
+}
+
+impl<T: Write> LenIO<T> {
+	// Queues up a frame, and sends as much as it can right away. The rest gets
+	// sent by later calls to write or flush.
+	pub fn write(&mut self, bytes: &[u8]) -> Result<()> {
+		let len = bytes.len();
+		if len > MAX_LENGTH {
+			return Err(Error::new(ErrorKind::InvalidInput, "Frame is too long"));
+		}
+		self.write_buf.extend_from_slice(&(len as u32).to_be_bytes());
+		self.write_buf.extend_from_slice(bytes);
+		self.flush()
+	}
+	// Sends as much of the queued up data as the inner writer accepts.
+	pub fn flush(&mut self) -> Result<()> {
+		while !self.write_buf.is_empty() {
+			match self.inner.write(&self.write_buf) {
+				Ok(0) => return Err(Error::new(ErrorKind::WriteZero, "Couldn't write data")),
+				Ok(bytes) => {self.write_buf.drain(..bytes);}
+				Err(err) if err.kind() == ErrorKind::Interrupted => {}
+				Err(err) if err.kind() == ErrorKind::WouldBlock => return Ok(()),
+				Err(err) => return Err(err),
+			}
+		}
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	
+	// Hands out one byte per read, with a WouldBlock in between every two,
+	// like a slow non-blocking socket.
+	struct Trickle {
+		bytes: Vec<u8>,
+		pos: usize,
+		blocked: bool,
+	}
+	
+	impl Read for Trickle {
+		fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+			self.blocked = !self.blocked;
+			if self.blocked {
+				return Err(Error::new(ErrorKind::WouldBlock, "Would block"));
+			}
+			if self.pos == self.bytes.len() {
+				return Ok(0);
+			}
+			buf[0] = self.bytes[self.pos];
+			self.pos += 1;
+			Ok(1)
+		}
+	}
+	
+	// Takes one byte per write, with a WouldBlock in between every two.
+	#[derive(Default)]
+	struct Sink {
+		bytes: Vec<u8>,
+		blocked: bool,
+	}
+	
+	impl Write for Sink {
+		fn write(&mut self, buf: &[u8]) -> Result<usize> {
+			self.blocked = !self.blocked;
+			if self.blocked {
+				return Err(Error::new(ErrorKind::WouldBlock, "Would block"));
+			}
+			self.bytes.push(buf[0]);
+			Ok(1)
+		}
+		fn flush(&mut self) -> Result<()> {Ok(())}
+	}
+	
+	fn read_frame(lenio: &mut LenIO<Trickle>) -> Result<Vec<u8>> {
+		loop {
+			match lenio.read() {
+				Err(err) if err.kind() == ErrorKind::WouldBlock => {}
+				result => return result.map(|frame|frame.to_vec()),
+			}
+		}
+	}
+	
+	#[test]
+	fn writes_and_reads_frames_a_byte_at_a_time() {
+		let frames = [vec![1, 2, 3], vec![], vec![7; CHUNK_LENGTH * 3]];
+		
+		let mut writer = LenIO::new(Sink::default());
+		for frame in &frames {
+			writer.write(frame).unwrap();
+		}
+		while !writer.write_buf.is_empty() {
+			writer.flush().unwrap();
+		}
+		
+		let bytes = writer.inner.bytes;
+		assert_eq!(bytes.len(), frames.iter().map(|frame|HEADER_LENGTH + frame.len()).sum::<usize>());
+		let mut reader = LenIO::new(Trickle {bytes, pos: 0, blocked: false});
+		for frame in &frames {
+			assert_eq!(&read_frame(&mut reader).unwrap(), frame);
+		}
+		assert_eq!(read_frame(&mut reader).unwrap_err().kind(), ErrorKind::UnexpectedEof);
+	}
+	
+	#[test]
+	fn rejects_frames_that_are_too_long() {
+		let mut writer = LenIO::new(Sink::default());
+		let err = writer.write(&vec![0; MAX_LENGTH + 1]).unwrap_err();
+		assert_eq!(err.kind(), ErrorKind::InvalidInput);
+		
+		let bytes = ((MAX_LENGTH + 1) as u32).to_be_bytes().to_vec();
+		let mut reader = LenIO::new(Trickle {bytes, pos: 0, blocked: false});
+		assert_eq!(read_frame(&mut reader).unwrap_err().kind(), ErrorKind::InvalidData);
+	}
+}
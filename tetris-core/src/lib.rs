@@ -0,0 +1,106 @@
+// The rules of the game, and everything else that doesn't need SDL, so that
+// the server, and anything else that wants to, can run the game too.
+
+use serde::{Serialize, Deserialize};
+use std::net::SocketAddr;
+
+#[macro_use]
+pub mod vec2;
+pub mod mino;
+pub mod block;
+pub mod lenio;
+pub mod game;
+pub mod rotation;
+pub mod gravity;
+pub mod mino_controller;
+pub mod unit;
+pub mod ui;
+pub mod network;
+pub mod room;
+pub mod lockstep;
+pub mod command;
+pub mod replay;
+pub mod simulation;
+pub mod bot;
+pub mod finesse;
+pub use vec2::{vec2i,vec2f};
+use mino::Mino;
+use ui::Pause;
+
+pub enum State {
+	Play {
+		players_won: u32,
+		players_done: u32,
+		players_lost: u32,
+		over: bool,
+		winner: Option<String>,
+		pause: Option<Pause>,
+	},
+	Title,
+	PreLobby,
+	Lobby,
+}
+
+impl State {
+	pub fn play() -> Self {
+		State::Play {
+			players_won: 0,
+			players_done: 0,
+			players_lost: 0,
+			over: false,
+			winner: None,
+			pause: None,
+		}
+	}
+}
+
+#[derive(Debug,Clone,Serialize,Deserialize)]
+pub struct InputMethod {
+	pub keyboard: Option<()>,
+	pub controller: Option<usize>,
+}
+impl InputMethod {
+	pub fn new(keyboard: bool, controller: Option<usize>) -> Self {
+		let keyboard = if keyboard {Some(())} else {None};
+		Self {keyboard, controller}
+	}
+}
+
+#[derive(Debug,Clone,Serialize,Deserialize)]
+pub enum PlayerKind {Local(InputMethod), Cpu(bot::Difficulty), Network}
+
+impl Default for PlayerKind {fn default() -> Self {PlayerKind::Network}}
+
+#[derive(Debug,Clone,Serialize,Deserialize,Default)]
+pub struct Player {
+	#[serde(skip)]pub kind: PlayerKind,
+	pub name: String,
+	// Only matters in online games, where the host waits for everyone to be
+	// ready before starting.
+	pub ready: bool,
+	// On the host, the client that the player was added through.
+	#[serde(skip)]pub peer: Option<SocketAddr>,
+}
+
+impl Player {
+	pub fn new(name: String, input: InputMethod) -> Player {
+		Player {
+			kind: PlayerKind::Local(input),
+			name,
+			ready: false,
+			peer: None,
+		}
+	}
+	pub fn cpu(name: String, difficulty: bot::Difficulty) -> Player {
+		Player {
+			kind: PlayerKind::Cpu(difficulty),
+			name,
+			// Nobody's there to get it ready, and it reaches the host as a
+			// network player.
+			ready: true,
+			peer: None,
+		}
+	}
+}
+
+pub const MAX_PLAYERS: usize = 8;
\ No newline at end of file
@@ -0,0 +1,417 @@
+use std::{collections::{BTreeMap, VecDeque}, time::Duration};
+use itertools::izip;
+use serde::{Serialize, Deserialize};
+use crate::command::Command;
+use crate::unit::{self, Unit, UnitCommandKind};
+
+// How often the units get checksummed, in frames.
+const CHECKSUM_INTERVAL: u64 = 60;
+// Checkpoints are kept around for a while, for checksums that come in late,
+// and for fixing desyncs.
+const CHECKPOINTS_KEPT: usize = 8;
+
+// The commands that a unit's mino controller made on one frame.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Input {
+	pub unit_id: usize,
+	pub frame: u64,
+	pub commands: Vec<UnitCommandKind>,
+}
+
+// Settings that every machine has to simulate with, for the units to end up
+// the same everywhere.
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize)]
+pub struct Timing {
+	pub frame_duration: Duration,
+	pub line_clear_duration: Duration,
+	pub game_of_life_duration: Duration,
+}
+
+// Checksums of the units at the start of a frame.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Checksum {
+	pub frame: u64,
+	pub checksums: Vec<u64>,
+}
+
+// A unit as the host had it at the start of a frame. Everyone goes back to
+// that frame, puts it in, and simulates from there again.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Sync {
+	pub frame: u64,
+	pub unit_id: usize,
+	pub base: unit::Base,
+	pub commands: VecDeque<UnitCommandKind>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Desync {
+	pub frame: u64,
+	pub unit_id: usize,
+	pub local: u64,
+	pub remote: u64,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct Checkpoint {
+	frame: u64,
+	bases: Vec<unit::Base>,
+	commands: Vec<VecDeque<UnitCommandKind>>,
+	checksums: Vec<u64>,
+}
+
+// Every machine simulates every unit from the inputs of all of the players. A
+// frame only gets simulated once the inputs of every unit are in, so it plays
+// out the same way everywhere.
+//
+// Local inputs are made input_delay frames ahead, which hides the time it takes
+// for them to get to the other machines. With a rollback window, the units are
+// also predicted past the last simulated frame, as if the missing inputs were
+// empty, and that's redone from the last simulated frame every frame.
+//
+// Whoever joins in the middle of a game gets this along with the units, to
+// carry on simulating from where the host is.
+#[derive(Default, Clone, Serialize, Deserialize)]
+pub struct Lockstep {
+	// The next frame to simulate.
+	pub frame: u64,
+	// The frame that the next local inputs are for.
+	pub input_frame: u64,
+	input_delay: u64,
+	inputs: Vec<BTreeMap<u64, Vec<UnitCommandKind>>>,
+	// The frame on which a unit lost its player, after which its inputs are
+	// no longer waited for.
+	disconnected: Vec<Option<u64>>,
+	// The units as they're predicted to be on input_frame.
+	#[serde(skip)]
+	pub predicted: Option<Vec<Unit>>,
+	
+	checkpoints: VecDeque<Checkpoint>,
+	// Checksums from the other machines, for frames that weren't simulated
+	// here yet.
+	remote_checksums: BTreeMap<u64, Vec<Vec<u64>>>,
+	syncs: Vec<Sync>,
+	// The last frame that was checksummed, so that going back to fix a desync
+	// doesn't send the checksums again.
+	last_checksum_frame: Option<u64>,
+	// Checksums to send to the other machines.
+	#[serde(skip)]
+	pub new_checksums: Vec<Checksum>,
+	#[serde(skip)]
+	pub desyncs: Vec<Desync>,
+	// Set when the units were fixed after a desync.
+	#[serde(skip)]
+	pub just_synced: bool,
+}
+
+impl Lockstep {
+	pub fn new(units: usize, input_delay: u64) -> Self {
+		// Nobody makes inputs for the frames before the delay, so they're empty.
+		let inputs = (0..units)
+			.map(|_|(0..input_delay).map(|frame|(frame, Vec::new())).collect())
+			.collect();
+		Lockstep {
+			frame: 0,
+			input_frame: input_delay,
+			input_delay,
+			inputs,
+			disconnected: vec![None; units],
+			predicted: None,
+			..Lockstep::default()
+		}
+	}
+	pub fn add_input(&mut self, input: Input) {
+		if input.frame < self.frame {return}
+		if let Some(inputs) = self.inputs.get_mut(input.unit_id) {
+			inputs.insert(input.frame, input.commands);
+		}
+	}
+	// Whether local inputs can be made, without getting more than the delay and
+	// the rollback window ahead of the other players.
+	pub fn can_input(&self, rollback: u64) -> bool {
+		self.input_frame <= self.frame + self.input_delay + rollback
+	}
+	// Every machine has all of the unit's inputs by the time that it gets
+	// this, so they all pick the same frame. That frame is returned.
+	pub fn disconnect(&mut self, unit_id: usize) -> Option<u64> {
+		let frame = self.inputs.get(unit_id)?.keys().next_back().map_or(self.frame, |frame|frame+1);
+		self.disconnect_at(unit_id, frame);
+		Some(frame)
+	}
+	pub fn disconnect_at(&mut self, unit_id: usize, frame: u64) {
+		if let Some(disconnected) = self.disconnected.get_mut(unit_id) {
+			*disconnected = Some(frame);
+		}
+	}
+	// Once every unit lost its player, there's nothing left to wait for, so
+	// that's where it stops.
+	fn is_ready(&self) -> bool {
+		izip!(&self.inputs, &self.disconnected).all(|(inputs, disconnected)|
+			inputs.contains_key(&self.frame) || disconnected.map_or(false, |disconnected|disconnected <= self.frame)) &&
+		self.inputs.iter().any(|inputs|inputs.contains_key(&self.frame))
+	}
+	fn start_frame(&self, frame: u64, units: &mut [Unit]) {
+		for (unit, disconnected) in izip!(units, &self.disconnected) {
+			if *disconnected == Some(frame) {
+				unit.base.lose();
+			}
+		}
+	}
+	// Simulates every frame that all of the inputs are in for.
+	pub fn advance(&mut self, units: &mut [Unit], commands: &mut [VecDeque<UnitCommandKind>], timing: &Timing) {
+		while self.advance_frame(units, commands, timing) {}
+	}
+	// Simulates the next frame, if all of its inputs are in.
+	pub fn advance_frame(&mut self, units: &mut [Unit], commands: &mut [VecDeque<UnitCommandKind>], timing: &Timing) -> bool {
+		let ready = self.is_ready();
+		if ready {
+			self.step(units, commands, timing);
+		}
+		ready
+	}
+	fn step(&mut self, units: &mut [Unit], commands: &mut [VecDeque<UnitCommandKind>], timing: &Timing) {
+		let frame = self.frame;
+		for sync in &self.syncs {
+			if sync.frame == frame {
+				units[sync.unit_id].base = sync.base.clone();
+				commands[sync.unit_id] = sync.commands.clone();
+			}
+		}
+		self.syncs.retain(|sync|sync.frame > frame);
+		if frame % CHECKSUM_INTERVAL == 0 {
+			self.checkpoint(units, commands);
+		}
+		self.start_frame(frame, units);
+		let inputs = self.inputs.iter()
+			.map(|inputs|inputs.get(&frame).cloned().unwrap_or_default())
+			.collect();
+		simulate_frame(units, commands, inputs, timing);
+		self.frame += 1;
+	}
+	fn checkpoint(&mut self, units: &[Unit], commands: &[VecDeque<UnitCommandKind>]) {
+		let frame = self.frame;
+		let checksums: Vec<_> = units.iter().map(|unit|unit.base.get_checksum()).collect();
+		if self.last_checksum_frame.map_or(true, |last|frame > last) {
+			self.last_checksum_frame = Some(frame);
+			self.new_checksums.push(Checksum {frame, checksums: checksums.clone()});
+		}
+		
+		self.checkpoints.retain(|checkpoint|checkpoint.frame < frame);
+		self.checkpoints.push_back(Checkpoint {
+			frame,
+			bases: units.iter().map(|unit|unit.base.clone()).collect(),
+			commands: commands.to_vec(),
+			checksums,
+		});
+		if self.checkpoints.len() > CHECKPOINTS_KEPT {
+			self.checkpoints.pop_front();
+		}
+		// Inputs from before the oldest checkpoint can't be needed anymore.
+		let oldest = self.checkpoints.front().unwrap().frame;
+		for inputs in &mut self.inputs {
+			*inputs = inputs.split_off(&oldest);
+		}
+		
+		if let Some(remote_checksums) = self.remote_checksums.remove(&frame) {
+			for checksums in remote_checksums {
+				self.compare_checksums(frame, &checksums);
+			}
+		}
+	}
+	fn compare_checksums(&mut self, frame: u64, remote: &[u64]) {
+		let checkpoint = match self.checkpoints.iter().find(|checkpoint|checkpoint.frame == frame) {
+			Some(checkpoint) => checkpoint,
+			None => return,
+		};
+		for (unit_id, local, remote) in izip!(0.., &checkpoint.checksums, remote) {
+			if local != remote {
+				self.desyncs.push(Desync {frame, unit_id, local: *local, remote: *remote});
+			}
+		}
+	}
+	pub fn add_checksum(&mut self, checksum: Checksum) {
+		let Checksum {frame, checksums} = checksum;
+		if self.checkpoints.iter().any(|checkpoint|checkpoint.frame == frame) {
+			self.compare_checksums(frame, &checksums);
+		}else if frame >= self.frame {
+			self.remote_checksums.entry(frame).or_default().push(checksums);
+		}
+	}
+	// What the host sends out to fix a desync.
+	pub fn get_sync(&self, frame: u64, unit_id: usize) -> Option<Sync> {
+		let checkpoint = self.checkpoints.iter().find(|checkpoint|checkpoint.frame == frame)?;
+		Some(Sync {
+			frame,
+			unit_id,
+			base: checkpoint.bases.get(unit_id)?.clone(),
+			commands: checkpoint.commands.get(unit_id)?.clone(),
+		})
+	}
+	pub fn sync(&mut self, sync: Sync, units: &mut [Unit], commands: &mut [VecDeque<UnitCommandKind>], timing: &Timing) {
+		if sync.unit_id >= units.len() {return}
+		if sync.frame >= self.frame {
+			self.syncs.push(sync);
+			return;
+		}
+		let checkpoint = match self.checkpoints.iter().find(|checkpoint|checkpoint.frame == sync.frame) {
+			Some(checkpoint) => checkpoint.clone(),
+			None => return,
+		};
+		
+		// Goes back to the checkpoint, and simulates up to where it was again.
+		let frame = self.frame;
+		for (unit, base) in izip!(units.iter_mut(), checkpoint.bases) {
+			unit.base = base;
+		}
+		for (commands, checkpoint_commands) in izip!(commands.iter_mut(), checkpoint.commands) {
+			*commands = checkpoint_commands;
+		}
+		self.frame = sync.frame;
+		self.syncs.push(sync);
+		while self.frame < frame {
+			self.step(units, commands, timing);
+		}
+		// Whatever happened on the way was already shown when it happened
+		// the first time.
+		for unit in units {
+			unit.base.reset_flags();
+		}
+		self.just_synced = true;
+	}
+	// Simulates a copy of the units up to input_frame, with the inputs that
+	// are in so far.
+	pub fn predict(&mut self, units: &[Unit], commands: &[VecDeque<UnitCommandKind>], timing: &Timing) {
+		let mut units = units.to_vec();
+		let mut commands = commands.to_vec();
+		for frame in self.frame..self.input_frame {
+			self.start_frame(frame, &mut units);
+			let inputs = self.inputs.iter()
+				.map(|inputs|inputs.get(&frame).cloned().unwrap_or_default())
+				.collect();
+			simulate_frame(&mut units, &mut commands, inputs, timing);
+		}
+		self.predicted = Some(units);
+	}
+}
+
+pub fn simulate_frame(units: &mut [Unit], commands: &mut [VecDeque<UnitCommandKind>], inputs: Vec<Vec<UnitCommandKind>>, timing: &Timing) {
+	for (unit, commands, inputs) in izip!(units.iter_mut(), commands.iter_mut(), inputs) {
+		if let unit::State::Animation {countdown} = &mut unit.base.state {
+			*countdown += timing.frame_duration;
+			if unit.base.lc_animation.is_some() {
+				if *countdown >= timing.line_clear_duration {
+					unit.base.state = unit::State::Play;
+				}
+			}else if unit.base.gol_animation.is_some() {
+				if *countdown >= timing.game_of_life_duration {
+					unit.base.state = unit::State::Play;
+				}
+			}
+		}
+		commands.extend(inputs);
+	}
+	
+	// We loop as long as there are new commands
+	let mut keep_looping = true;
+	while keep_looping {
+		keep_looping = false;
+		for (unit_id, unit) in units.iter_mut().enumerate() {
+			// This while loop is ugly. Refactor it when this
+			// https://github.com/rust-lang/rust/issues/53667 gets added
+			while !commands[unit_id].is_empty() && matches!(unit.base.state, unit::State::Play) {
+				keep_looping = true;
+				let command = commands[unit_id].pop_front().unwrap();
+				(unit_id, command).execute(|(unit_id, command)|commands[unit_id].push_back(command), unit);
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{Player, room::Room, simulation::Simulation};
+	
+	const INPUT_DELAY: u64 = 3;
+	
+	// Two network players, like on every machine other than theirs.
+	fn create_game(seed: u64) -> (Lockstep, Vec<Unit>, Vec<VecDeque<UnitCommandKind>>) {
+		let mut room = Room::new();
+		room.players = vec![Player::default(), Player::default()];
+		let Simulation {units, commands, ..} = Simulation::new(&room, seed);
+		(Lockstep::new(units.len(), INPUT_DELAY), units, commands)
+	}
+	
+	// Some made up inputs, the same for every call with the same frame.
+	fn create_inputs(frame: u64, unit_id: usize) -> Vec<UnitCommandKind> {
+		use UnitCommandKind::*;
+		let n = (frame * 7 + unit_id as u64 * 3) % 11;
+		match n {
+			0 => vec![MoveLeft, RotateRight],
+			1 | 2 => vec![MoveRight],
+			3 => vec![RotateLeft, MoveLeft],
+			4 => vec![SoftDrop(1)],
+			5 if frame % 4 == 0 => vec![HardDrop],
+			_ => vec![ApplyGravity(1)],
+		}
+	}
+	
+	#[test]
+	fn same_inputs_same_checksums() {
+		let timing = Timing::default();
+		let (mut host, mut host_units, mut host_commands) = create_game(5);
+		let (mut client, mut client_units, mut client_commands) = create_game(5);
+		
+		for frame in INPUT_DELAY..CHECKSUM_INTERVAL*10 {
+			// Every machine gets the inputs in a different order.
+			for unit_id in [0, 1] {
+				host.add_input(Input {unit_id, frame, commands: create_inputs(frame, unit_id)});
+			}
+			for unit_id in [1, 0] {
+				client.add_input(Input {unit_id, frame, commands: create_inputs(frame, unit_id)});
+			}
+			host.advance(&mut host_units, &mut host_commands, &timing);
+			client.advance(&mut client_units, &mut client_commands, &timing);
+			
+			for checksum in client.new_checksums.drain(..) {
+				host.add_checksum(checksum);
+			}
+			for checksum in host.new_checksums.drain(..) {
+				client.add_checksum(checksum);
+			}
+		}
+		
+		assert_eq!(host.frame, CHECKSUM_INTERVAL*10);
+		assert_eq!(client.frame, host.frame);
+		assert!(host_units.iter().any(|unit|unit.base.score > 0));
+		assert_eq!(host.desyncs, Vec::new());
+		assert_eq!(client.desyncs, Vec::new());
+		for (host_unit, client_unit) in izip!(&host_units, &client_units) {
+			assert_eq!(host_unit.base.get_checksum(), client_unit.base.get_checksum());
+		}
+	}
+	
+	#[test]
+	fn different_inputs_are_a_desync() {
+		let timing = Timing::default();
+		let (mut host, mut host_units, mut host_commands) = create_game(5);
+		let (mut client, mut client_units, mut client_commands) = create_game(5);
+		
+		for frame in INPUT_DELAY..CHECKSUM_INTERVAL+1 {
+			for unit_id in [0, 1] {
+				host.add_input(Input {unit_id, frame, commands: create_inputs(frame, unit_id)});
+				// The client misses every hard drop.
+				let mut commands = create_inputs(frame, unit_id);
+				commands.retain(|command|!matches!(command, UnitCommandKind::HardDrop));
+				client.add_input(Input {unit_id, frame, commands});
+			}
+		}
+		host.advance(&mut host_units, &mut host_commands, &timing);
+		client.advance(&mut client_units, &mut client_commands, &timing);
+		for checksum in client.new_checksums.drain(..) {
+			host.add_checksum(checksum);
+		}
+		
+		assert!(host.desyncs.iter().any(|desync|desync.frame == CHECKSUM_INTERVAL));
+	}
+}
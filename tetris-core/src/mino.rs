@@ -0,0 +1,82 @@
+#![allow(dead_code)]
+use std::cmp::{max, min};
+
+use crate::vec2::vec2i;
+use crate::block::Data;
+use serde::{Serialize,Deserialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Shape {L, J, O, Z, S, T, I}
+
+impl Shape {
+	pub const ALL: [Shape; 7] = [Shape::L, Shape::J, Shape::O, Shape::Z, Shape::S, Shape::T, Shape::I];
+	
+	pub fn data(&self) -> Data {
+		match self {
+			Shape::L => Data::BLUE,
+			Shape::J => Data::ORANGE,
+			Shape::O => Data::YELLOW,
+			Shape::Z => Data::PURPLE,
+			Shape::S => Data::GREEN,
+			Shape::T => Data::PINK,
+			Shape::I => Data::CYAN,
+		}
+	}
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Mino {
+	pub shape: Shape,
+	// Top left corner of the mino's bounding box, the rotation states are
+	// relative to it.
+	pub pos: vec2i,
+	pub rotation: i32,
+	pub blocks: [vec2i; 4],
+	pub blocks_data: [Data; 4],
+	// Set only if the last successful move was a rotation, to the kick offset
+	// that it used.
+	pub last_kick: Option<vec2i>,
+}
+impl Mino {
+	pub fn new(shape: Shape, blocks: [vec2i; 4]) -> Self {
+		Mino {shape, pos: vec2i::ZERO, rotation:0, blocks, blocks_data:[shape.data(); 4], last_kick: None}
+	}
+	
+	pub fn translate(&mut self, v: vec2i) {
+		self.pos += v;
+		self.last_kick = None;
+		for block in self.blocks.iter_mut() {
+			*block += v;
+		}
+	}
+	pub fn right(&mut self) {
+		self.translate(vec2i!(1,0));
+	}
+	pub fn left(&mut self) {
+		self.translate(vec2i!(-1,0));
+	}
+	pub fn down(&mut self) {
+		self.translate(vec2i!(0,1));
+	}
+	
+	pub fn get_rect(&self) -> (vec2i,vec2i) {
+		let mut iter = self.blocks.iter();
+		let mut hi = iter.next().unwrap().clone();
+		let mut lo = hi;
+		for v in iter {
+			hi.x = max(hi.x, v.x);
+			hi.y = max(hi.y, v.y);
+			lo.x = min(lo.x, v.x);
+			lo.y = min(lo.y, v.y);
+		}
+		(lo,hi)
+	}
+	pub fn get_size(&self) -> vec2i {
+		let (lo,hi) = self.get_rect();
+		hi-lo+vec2i!(1,1)
+	}
+	
+	pub fn make_shadow(&mut self) {
+		self.blocks_data = [Data::SHADOW; 4];
+	}
+}
\ No newline at end of file
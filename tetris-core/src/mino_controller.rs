@@ -0,0 +1,235 @@
+use std::time::Duration;
+use crate::unit::{self,UnitCommandKind};
+use crate::{game, gravity};
+use serde::{Serialize,Deserialize};
+
+#[derive(Clone, Serialize, Deserialize)]
+pub enum RotDirection {None, Left, Right}
+
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MoveState {Still, Instant, Prepeat, Repeat}
+
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum MoveDirection {None, Left, Right}
+
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FallState {Fall, Softdrop, Harddrop}
+
+// The player's settings for how their mino moves.
+#[derive(Debug, Clone, Copy)]
+pub struct Handling {
+	pub move_prepeat_duration: Duration,
+	pub move_repeat_duration: Duration,
+	pub lock_delay: Duration,
+	pub max_lock_resets: u32,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct MinoController {
+	pub move_direction: MoveDirection,
+	pub move_state: MoveState,
+	pub rot_direction: RotDirection,
+	pub fall_state: FallState,
+	 
+	pub store: bool,
+	 
+	pub fall_countdown: Duration,
+	pub move_repeat_countdown: Duration,
+	pub lock_countdown: Duration,
+	// The unit's lock phase when lock_countdown was last started over.
+	#[serde(skip)]
+	pub lock_phase: Option<unit::LockPhase>,
+	 
+	pub fall_duration: Duration,
+	 
+	pub config_id: usize,
+}
+
+impl MinoController {
+	pub fn new(config_id: usize, gravity: gravity::Kind) -> Self {
+	   MinoController {
+			move_direction: MoveDirection::None,
+			move_state: MoveState::Still,
+			rot_direction: RotDirection::None,
+			fall_state: FallState::Fall,
+			
+			store: false,
+			
+			fall_countdown: Duration::from_secs(0),
+			move_repeat_countdown: Duration::from_secs(0),
+			lock_countdown: Duration::from_secs(0),
+			lock_phase: None,
+			
+			fall_duration: gravity.get_fall_duration(1),
+			
+			config_id,
+	   }
+	}
+	pub fn append_commands(&mut self, queue: &mut Vec<UnitCommandKind>, handling: &Handling, base: &unit::Base, dpf: Duration) {
+		let MinoController {
+			move_direction,
+			move_state,
+			rot_direction,
+			fall_state,
+			store,
+			move_repeat_countdown,
+			fall_countdown,
+			fall_duration,
+			lock_countdown,
+			lock_phase,
+			..
+		} = self;
+		
+		// Moving or rotating the mino on the ground gives it more time.
+		let phase = base.get_lock_phase();
+		if *lock_phase != Some(phase) {
+			*lock_phase = Some(phase);
+			*lock_countdown = Duration::from_secs(0);
+		}
+		
+		let mut append = |command|queue.push(command);
+		let move_repeat_duration = &handling.move_repeat_duration;
+		let move_prepeat_duration = &handling.move_prepeat_duration;
+		
+		// MOVEMENT
+		
+		if MoveState::Instant == *move_state {
+			match move_direction{
+				MoveDirection::Left => append(UnitCommandKind::MoveLeft),
+				MoveDirection::Right => append(UnitCommandKind::MoveRight),
+				_ => panic!(),
+			};
+			*move_repeat_countdown = Duration::from_secs(0);
+			*move_state = MoveState::Prepeat;
+		}
+		if MoveState::Prepeat == *move_state {
+			if *move_repeat_countdown >= *move_prepeat_duration {
+				*move_repeat_countdown -= *move_prepeat_duration;
+				match move_direction{
+					MoveDirection::Left => append(UnitCommandKind::RepeatLeft),
+					MoveDirection::Right => append(UnitCommandKind::RepeatRight),
+					_ => panic!(),
+				};
+				*move_state = MoveState::Repeat;
+			}
+		}
+		if MoveState::Repeat == *move_state {
+			while *move_repeat_countdown >= *move_repeat_duration {
+				*move_repeat_countdown -= *move_repeat_duration;
+				match move_direction{
+					MoveDirection::Left => append(UnitCommandKind::RepeatLeft),
+					MoveDirection::Right => append(UnitCommandKind::RepeatRight),
+					_ => panic!(),
+				};
+			}
+		}
+		if MoveState::Still != *move_state {
+			*move_repeat_countdown += dpf;
+		}
+		
+		// ROTATION
+		
+		match rot_direction {
+			RotDirection::Left => append(UnitCommandKind::RotateLeft),
+			RotDirection::Right => append(UnitCommandKind::RotateRight),
+			_ => (),
+		};
+		*rot_direction = RotDirection::None;
+		
+		// GRAVITY
+		
+		match fall_state {
+			FallState::Fall => *fall_countdown += dpf,
+			FallState::Softdrop => *fall_countdown += 3*dpf,
+			FallState::Harddrop => (),
+		}
+		
+		let grounded = base.falling_mino.as_ref()
+			.is_some_and(|mino|!game::may_down_mino(mino, &base.well));
+		
+		if FallState::Harddrop == *fall_state {
+			append(UnitCommandKind::HardDrop);
+			*fall_state = FallState::Fall;
+			*fall_countdown = Duration::from_secs(0);
+			*lock_countdown = Duration::from_secs(0);
+		}else if grounded {
+			// Gravity doesn't build up while the mino sits on the ground.
+			*fall_countdown = Duration::from_secs(0);
+			*lock_countdown += dpf;
+			if *lock_countdown >= handling.lock_delay || base.lock_resets >= handling.max_lock_resets {
+				append(UnitCommandKind::Lock);
+				*lock_countdown = Duration::from_secs(0);
+			}
+		}else {
+			*lock_countdown = Duration::from_secs(0);
+			let mut g = 0;
+			if fall_duration.is_zero() {
+				g = i32::MAX;
+				*fall_countdown = Duration::from_secs(0);
+			}else {
+				while *fall_countdown >= *fall_duration {
+					g += 1;
+					*fall_countdown -= *fall_duration;
+				}
+			}
+		
+			if g != 0 {
+				if FallState::Softdrop == *fall_state {
+					append(UnitCommandKind::SoftDrop(g));
+				}else {
+					append(UnitCommandKind::ApplyGravity(g));
+				}
+			}
+		}
+		
+		if *store {
+			append(UnitCommandKind::Store);
+			*store = false;
+		}
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::command::Command;
+	use crate::unit::{Settings, Unit};
+	use crate::mino::Shape;
+	
+	const DPF: Duration = Duration::from_millis(10);
+	
+	fn create_handling() -> Handling {
+		Handling {
+			move_prepeat_duration: Duration::from_millis(150),
+			move_repeat_duration: Duration::from_millis(50),
+			lock_delay: DPF*10,
+			max_lock_resets: 15,
+		}
+	}
+	
+	// Runs the controller for a number of frames, without simulating what it
+	// sends, and returns whether it locked the mino.
+	fn run(mino_controller: &mut MinoController, unit: &Unit, frames: u32) -> bool {
+		let mut commands = Vec::new();
+		for _ in 0..frames {
+			mino_controller.append_commands(&mut commands, &create_handling(), &unit.base, DPF);
+		}
+		commands.iter().any(|command|matches!(command, UnitCommandKind::Lock))
+	}
+	
+	#[test]
+	fn moves_on_the_ground_reset_the_lock_delay() {
+		let mut unit = Unit::network(Settings::default(), game::MinoRng::new(game::Randomizer::default(), 0));
+		let mut mino = unit.base.rotation_system.system().spawn_mino(Shape::T);
+		game::center_mino(&mut mino, &unit.base.well_size);
+		unit.base.set_falling_mino(mino);
+		unit.base.fall(i32::MAX);
+		let mut mino_controller = MinoController::new(0, gravity::Kind::Classic);
+		
+		assert!(!run(&mut mino_controller, &unit, 8));
+		(0, UnitCommandKind::MoveLeft).execute(|_|{}, &mut unit);
+		assert!(!run(&mut mino_controller, &unit, 8));
+		assert!(run(&mut mino_controller, &unit, 3));
+	}
+}
@@ -0,0 +1,369 @@
+use std::{io::{self, ErrorKind}, mem, net::{SocketAddr, TcpListener, TcpStream}, time::Duration};
+use bincode::{deserialize, serialize};
+use crate::{command::{Command, CommandWrapper}, lenio::LenIO, room::{Room, RoomCommand}};
+use crate::{rotation, gravity, game::Randomizer, unit::Hold};
+use serde::{Serialize, Deserialize};
+
+// Bump this whenever NetworkCommand, or anything sent inside it, changes.
+pub const PROTOCOL_VERSION: u32 = 5;
+pub const GAME_VERSION: &str = env!("CARGO_PKG_VERSION");
+const MAGIC: [u8; 4] = *b"TTT3";
+// How long a rejected client gets to receive the reason.
+const REJECT_TIMEOUT: Duration = Duration::from_secs(1);
+// Rules the host can pick for a game. Both sides say which ones they support,
+// and a client only gets in if it supports the ones that the room uses.
+const RULE_SET: &[&str] = &[
+	"srs", "ars", "nrs",
+	"classic", "worldwide", "tgm", "20g",
+	"7-bag", "14-bag", "tgm-history", "tgm3", "nes", "hard",
+	"hold-off", "hold-once", "hold-unlimited", "hold-two-slot",
+	"lock-delay", "top-out-rules", "well-size", "preview",
+];
+
+fn get_rules(room: &Room) -> Vec<&'static str> {
+	let rotation_system = match room.selected_rotation_system {
+		rotation::Kind::Srs => "srs",
+		rotation::Kind::Ars => "ars",
+		rotation::Kind::Nrs => "nrs",
+	};
+	let gravity = match room.gravity {
+		gravity::Kind::Classic => "classic",
+		gravity::Kind::Worldwide => "worldwide",
+		gravity::Kind::Tgm => "tgm",
+		gravity::Kind::Instant => "20g",
+	};
+	let randomizer = match room.randomizer {
+		Randomizer::Fair => "7-bag",
+		Randomizer::Bag14 => "14-bag",
+		Randomizer::TgmHistory => "tgm-history",
+		Randomizer::Tgm3 => "tgm3",
+		Randomizer::Nes => "nes",
+		Randomizer::Hard => "hard",
+	};
+	let hold = match room.hold {
+		Hold::Off => "hold-off",
+		Hold::Once => "hold-once",
+		Hold::Unlimited => "hold-unlimited",
+		Hold::TwoSlot => "hold-two-slot",
+	};
+	vec![
+		rotation_system, gravity, randomizer, hold,
+		"lock-delay", "top-out-rules", "well-size", "preview",
+	]
+}
+
+// The first frame sent both ways, before anything else. It's never changed,
+// so that older or newer versions can still read it, and say what's wrong.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Handshake {
+	magic: [u8; 4],
+	pub protocol_version: u32,
+	pub game_version: String,
+	pub rule_set: Vec<String>,
+	pub name: String,
+}
+
+impl Handshake {
+	pub fn new(name: &str) -> Self {
+		Handshake {
+			magic: MAGIC,
+			protocol_version: PROTOCOL_VERSION,
+			game_version: GAME_VERSION.to_string(),
+			rule_set: RULE_SET.iter().map(|rule|rule.to_string()).collect(),
+			name: name.to_string(),
+		}
+	}
+	// Returns why the other side can't talk to this one, if it can't.
+	pub fn check(&self) -> Result<(), String> {
+		if self.magic != MAGIC {
+			return Err("Not a tetris-the-third game".to_string());
+		}
+		if self.protocol_version != PROTOCOL_VERSION {
+			return Err(format!(
+				"Protocol version {} doesn't match {} (game version {} and {})",
+				self.protocol_version, PROTOCOL_VERSION, self.game_version, GAME_VERSION));
+		}
+		Ok(())
+	}
+	// Returns the rules of the room that the other side doesn't support.
+	pub fn check_rules(&self, room: &Room) -> Result<(), String> {
+		let missing: Vec<_> = get_rules(room).into_iter()
+			.filter(|rule|!self.rule_set.iter().any(|other|other == rule))
+			.collect();
+		if !missing.is_empty() {
+			return Err(format!("Missing support for {}", missing.join(", ")));
+		}
+		Ok(())
+	}
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub enum HandshakeReply {
+	Accept(Handshake),
+	Reject(String),
+}
+
+pub enum NetworkEvent {
+	Command(NetworkCommand),
+	// A client left. The players it added have it as their peer.
+	ClientLeft(SocketAddr),
+	HostLeft,
+}
+
+pub enum HandshakeEvent {
+	// Has the name of the other side.
+	Accepted(String),
+	// Has the reason.
+	Rejected(String),
+}
+
+#[derive(Serialize, Deserialize)]
+pub enum NetworkCommand {
+	RoomCommand(CommandWrapper<RoomCommand>),
+	// Sent by a client right after its handshake, to only watch. The host
+	// ignores everything else that it sends.
+	Spectate,
+}
+impl From<CommandWrapper<RoomCommand>> for NetworkCommand {
+	fn from(other: CommandWrapper<RoomCommand>) -> Self {
+		NetworkCommand::RoomCommand(other)
+	}
+}
+
+// The other side of a connection.
+#[derive(Debug)]
+pub struct Peer {
+	stream: LenIO<TcpStream>,
+	addr: SocketAddr,
+	// Name from the handshake.
+	name: String,
+	spectator: bool,
+	// Set when reading or writing fails. The peer gets dropped on the next poll.
+	closed: bool,
+}
+
+impl Peer {
+	fn new(stream: LenIO<TcpStream>, addr: SocketAddr, name: String) -> Self {
+		Peer {stream, addr, name, spectator: false, closed: false}
+	}
+	fn write(&mut self, bytes: &[u8]) {
+		if self.stream.write(bytes).is_err() {
+			self.closed = true;
+		}
+	}
+}
+
+#[derive(Debug)]
+pub enum NetworkState {
+	Offline,
+	// Sent its handshake, and waits for the host to accept it.
+	Connecting {
+		stream: LenIO<TcpStream>,
+		addr: SocketAddr,
+	},
+	Client {
+		host: Peer,
+	},
+	Host {
+		listener: TcpListener,
+		peers: Vec<Peer>,
+		// Clients that haven't sent their handshake yet.
+		pending: Vec<(LenIO<TcpStream>, SocketAddr)>,
+	},
+}
+
+impl NetworkState {
+	pub fn broadcast<T: Into<NetworkCommand>+Clone>(&mut self, data: &T) {
+		let data: NetworkCommand = data.clone().into();
+		match self {
+			NetworkState::Offline | NetworkState::Connecting {..} => {},
+			NetworkState::Client {host} => {
+				host.write(&serialize(&data).unwrap());
+			}
+			NetworkState::Host {peers,..} => {
+				let data = &serialize(&data).unwrap();
+				for peer in peers {
+					peer.write(data);
+				}
+			}
+		}
+	}
+	
+	pub fn connect(stream: TcpStream, addr: SocketAddr, name: &str, spectator: bool) -> io::Result<Self> {
+		let mut stream = LenIO::new(stream);
+		stream.write(&serialize(&Handshake::new(name)).unwrap())?;
+		if spectator {
+			stream.write(&serialize(&NetworkCommand::Spectate).unwrap())?;
+		}
+		Ok(NetworkState::Connecting {stream, addr})
+	}
+	
+	pub fn get_spectator_count(&self) -> usize {
+		match self {
+			NetworkState::Host {peers, ..} => peers.iter().filter(|peer|peer.spectator).count(),
+			_ => 0,
+		}
+	}
+	
+	// Goes through the handshakes that arrived. The host answers them, and sends
+	// Init to the clients it accepts. The client becomes Client once accepted,
+	// or goes Offline if it's rejected.
+	pub fn poll_handshakes(&mut self, name: &str, room: &Room) -> Vec<HandshakeEvent> {
+		let mut events = Vec::new();
+		match self {
+			NetworkState::Host {peers, pending, ..} => {
+				let mut i = 0;
+				while i < pending.len() {
+					let result = match pending[i].0.read() {
+						Err(err) if err.kind() == ErrorKind::WouldBlock => {i += 1; continue}
+						Err(err) => Err(err.to_string()),
+						Ok(serialized) => deserialize::<Handshake>(serialized)
+							.map_err(|_|"Couldn't read handshake".to_string())
+							.and_then(|handshake|handshake.check().and_then(|_|handshake.check_rules(room)).map(|_|handshake)),
+					};
+					let (mut stream, addr) = pending.remove(i);
+					match result {
+						Ok(handshake) => {
+							// Someone rejoining, whose old connection hasn't
+							// timed out yet.
+							for peer in peers.iter_mut() {
+								if peer.name == handshake.name && peer.addr.ip() == addr.ip() {
+									peer.closed = true;
+								}
+							}
+							
+							// The client might have left already.
+							let written = stream.write(&serialize(&HandshakeReply::Accept(Handshake::new(name))).unwrap())
+								.and_then(|_|stream.write(
+									&serialize(
+										&NetworkCommand::from(RoomCommand::Init(room.snapshot()).wrap())
+									).unwrap()
+								));
+							match written {
+								Ok(()) => {
+									events.push(HandshakeEvent::Accepted(handshake.name.clone()));
+									peers.push(Peer::new(stream, addr, handshake.name));
+								}
+								Err(err) => events.push(HandshakeEvent::Rejected(err.to_string())),
+							}
+						}
+						Err(reason) => {
+							// The stream gets dropped right after this, so the reason
+							// has to be sent out before that.
+							let _ = stream.get_ref().set_nonblocking(false);
+							let _ = stream.get_ref().set_write_timeout(Some(REJECT_TIMEOUT));
+							let _ = stream.write(&serialize(&HandshakeReply::Reject(reason.clone())).unwrap());
+							events.push(HandshakeEvent::Rejected(reason));
+						}
+					}
+				}
+			}
+			NetworkState::Connecting {stream, ..} => {
+				let result = match stream.read() {
+					Err(err) if err.kind() == ErrorKind::WouldBlock => return events,
+					Err(err) => Err(err.to_string()),
+					Ok(serialized) => match deserialize::<HandshakeReply>(serialized) {
+						Ok(HandshakeReply::Accept(handshake)) => handshake.check().map(|_|handshake.name),
+						Ok(HandshakeReply::Reject(reason)) => Err(reason),
+						Err(_) => Err("Couldn't read the host's handshake".to_string()),
+					},
+				};
+				match result {
+					Ok(name) => {
+						events.push(HandshakeEvent::Accepted(name.clone()));
+						if let NetworkState::Connecting {stream, addr} = mem::replace(self, NetworkState::Offline) {
+							*self = NetworkState::Client {host: Peer::new(stream, addr, name)};
+						}
+					}
+					Err(reason) => {
+						*self = NetworkState::Offline;
+						events.push(HandshakeEvent::Rejected(reason));
+					}
+				}
+			}
+			NetworkState::Offline | NetworkState::Client {..} => {}
+		}
+		events
+	}
+}
+
+pub struct NetworkPump {
+	stream_index: usize,
+}
+
+impl NetworkPump {
+	pub fn new() -> NetworkPump {
+		NetworkPump {stream_index: 0}
+	}
+	pub fn poll(&mut self, state: &mut NetworkState) -> Option<NetworkEvent> {
+		let Self {stream_index} = self;
+		// Sends whatever couldn't be sent when it was written.
+		match state {
+			NetworkState::Offline => {}
+			NetworkState::Host {peers, pending, ..} => {
+				for peer in peers.iter_mut() {
+					if peer.stream.flush().is_err() {peer.closed = true}
+				}
+				for (stream, _) in pending.iter_mut() {
+					let _ = stream.flush();
+				}
+			}
+			NetworkState::Client {host} => {
+				if host.stream.flush().is_err() {host.closed = true}
+			}
+			NetworkState::Connecting {stream, ..} => {
+				let _ = stream.flush();
+			}
+		}
+		match state {
+			NetworkState::Offline | NetworkState::Connecting {..} => None,
+			NetworkState::Host {peers,..} => {
+				while *stream_index < peers.len() {
+					let (before, after) = peers.split_at_mut(*stream_index);
+					if let Some((peer, after)) = after.split_first_mut() {
+						match peer.stream.read() {
+							Ok(serialized) => {
+								if let Ok(mut deserialized) = deserialize::<NetworkCommand>(serialized) {
+									if let NetworkCommand::Spectate = deserialized {
+										peer.spectator = true;
+										continue;
+									}
+									if peer.spectator {continue}
+									for other in before.iter_mut().chain(after.iter_mut()) {
+										other.write(serialized);
+									}
+									if let NetworkCommand::RoomCommand(CommandWrapper {inner: RoomCommand::AddPlayer(player), ..}) = &mut deserialized {
+										player.peer = Some(peer.addr);
+									}
+									return Some(NetworkEvent::Command(deserialized));
+								}else {*stream_index += 1;}
+							}
+							Err(err) if err.kind() == ErrorKind::WouldBlock => *stream_index += 1,
+							Err(_) => {
+								peer.closed = true;
+								*stream_index += 1;
+							}
+						}
+					}
+				}
+				if let Some(index) = peers.iter().position(|peer|peer.closed) {
+					let peer = peers.remove(index);
+					*stream_index -= 1;
+					return Some(NetworkEvent::ClientLeft(peer.addr));
+				}
+				None
+			}
+			NetworkState::Client {host} => {
+				if !host.closed {
+					match host.stream.read() {
+						Ok(serialized) => return deserialize::<NetworkCommand>(serialized).ok().map(NetworkEvent::Command),
+						Err(err) if err.kind() == ErrorKind::WouldBlock => return None,
+						Err(_) => {}
+					}
+				}
+				*state = NetworkState::Offline;
+				Some(NetworkEvent::HostLeft)
+			}
+		}
+	}
+}
\ No newline at end of file
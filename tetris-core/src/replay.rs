@@ -0,0 +1,182 @@
+use serde::{Serialize, Deserialize};
+
+use crate::PlayerKind;
+use crate::lockstep::{Input, Sync};
+use crate::room::{Room, RoomCommand};
+
+// How many frames get played on every frame, while fast-forwarding.
+const FAST_FORWARD_SPEED: u32 = 4;
+
+// Everything that happened to the units, on the frame that it happened on.
+#[derive(Clone, Serialize, Deserialize)]
+pub enum Event {
+	Input(Input),
+	Sync(Sync),
+	Disconnect {frame: u64, unit_id: usize},
+}
+
+impl Event {
+	pub fn get_frame(&self) -> u64 {
+		match self {
+			Event::Input(input) => input.frame,
+			Event::Sync(sync) => sync.frame,
+			Event::Disconnect {frame, ..} => *frame,
+		}
+	}
+}
+
+// A game, as the room that it was started from, the seed that the minos came
+// from, and the events after that. Playing the events through a lockstep gets
+// the units to where they were, the same way that it does online.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Replay {
+	pub room: Box<Room>,
+	pub seed: u64,
+	pub events: Vec<Event>,
+}
+
+impl Replay {
+	pub fn new(room: &Room, seed: u64) -> Self {
+		let mut room = room.clone();
+		room.units.clear();
+		room.chat.clear();
+		room.lockstep = Default::default();
+		room.replay = None;
+		// The inputs all come from the events, so nobody gets to add more
+		// while it plays, not even the local players and bots that made them.
+		for player in &mut room.players {
+			player.kind = PlayerKind::Network;
+		}
+		Replay {room: Box::new(room), seed, events: Vec::new()}
+	}
+	pub fn record(&mut self, event: Event) {
+		self.events.push(event);
+	}
+}
+
+pub struct Playback {
+	replay: Replay,
+	next_event: usize,
+	pub paused: bool,
+	pub fast_forward: bool,
+	// Frames to play while paused.
+	steps: u32,
+}
+
+impl Playback {
+	pub fn new(mut replay: Replay) -> Self {
+		// Syncs come in after the frame that they're for, so they're put back
+		// in their place.
+		replay.events.sort_by_key(Event::get_frame);
+		Playback {replay, next_event: 0, paused: false, fast_forward: false, steps: 0}
+	}
+	// The commands that start the game again, from the beginning.
+	pub fn start(&mut self) -> Vec<RoomCommand> {
+		self.next_event = 0;
+		vec![
+			RoomCommand::Init((*self.replay.room).clone()),
+			RoomCommand::StartGame(self.replay.seed),
+		]
+	}
+	pub fn step(&mut self) {
+		self.steps += 1;
+	}
+	// Plays as many frames as should go by on this frame.
+	pub fn update(&mut self, room: &mut Room) {
+		let frames = if self.paused {
+			std::mem::take(&mut self.steps)
+		}else if self.fast_forward {
+			FAST_FORWARD_SPEED
+		}else {
+			1
+		};
+		for _ in 0..frames {
+			self.play_frame(room);
+		}
+	}
+	fn play_frame(&mut self, room: &mut Room) {
+		let frame = room.lockstep.frame;
+		while let Some(event) = self.replay.events.get(self.next_event) {
+			if event.get_frame() > frame {break}
+			match event.clone() {
+				Event::Input(input) => room.lockstep.add_input(input),
+				Event::Sync(sync) => room.lockstep.sync(sync, &mut room.units, &mut room.commands, &room.timing),
+				Event::Disconnect {frame, unit_id} => room.lockstep.disconnect_at(unit_id, frame),
+			}
+			self.next_event += 1;
+		}
+		room.lockstep.advance_frame(&mut room.units, &mut room.commands, &room.timing);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{InputMethod, Player, State, bot, command::Command, unit::{self, UnitCommandKind}};
+	
+	const FRAMES: u64 = 600;
+	
+	fn create_inputs(frame: u64, unit_id: usize) -> Vec<UnitCommandKind> {
+		use UnitCommandKind::*;
+		match (frame * 5 + unit_id as u64) % 9 {
+			0 => vec![MoveLeft, RotateLeft],
+			1 | 2 => vec![MoveRight],
+			3 => vec![RotateRight],
+			4 if frame % 3 == 0 => vec![HardDrop],
+			_ => vec![ApplyGravity(1)],
+		}
+	}
+	
+	#[test]
+	fn playback_ends_up_where_the_game_did() {
+		let mut room = Room::new();
+		let mut state = State::Lobby;
+		room.players = vec![Player::default(), Player::default()];
+		RoomCommand::StartGame(11).execute(|_|{}, (&mut room, &mut state));
+		for frame in 0..FRAMES {
+			if frame == FRAMES/2 {
+				RoomCommand::DisconnectPlayer(1).execute(|_|{}, (&mut room, &mut state));
+			}
+			for unit_id in 0..room.units.len() {
+				let input = Input {unit_id, frame, commands: create_inputs(frame, unit_id)};
+				RoomCommand::Input(input).execute(|_|{}, (&mut room, &mut state));
+			}
+			room.lockstep.advance(&mut room.units, &mut room.commands, &room.timing);
+		}
+		let frame = room.lockstep.frame;
+		let checksums: Vec<_> = room.units.iter().map(|unit|unit.base.get_checksum()).collect();
+		
+		// Saved and loaded, like it is from the file.
+		let bytes = bincode::serialize(room.replay.as_ref().unwrap()).unwrap();
+		let mut playback = Playback::new(bincode::deserialize(&bytes).unwrap());
+		let mut played_room = Room::new();
+		let mut played_state = State::Lobby;
+		for command in playback.start() {
+			command.execute(|_|{}, (&mut played_room, &mut played_state));
+		}
+		while played_room.lockstep.frame < frame {
+			playback.update(&mut played_room);
+		}
+		let played_checksums: Vec<_> = played_room.units.iter().map(|unit|unit.base.get_checksum()).collect();
+		assert_eq!(played_checksums, checksums);
+		assert!(matches!(played_room.units[1].base.state, crate::unit::State::Lose));
+	}
+	
+	#[test]
+	fn playback_has_no_players_to_add_inputs() {
+		let mut room = Room::new();
+		let mut state = State::Lobby;
+		room.players = vec![
+			Player::new(String::from("local"), InputMethod::new(true, None)),
+			Player::cpu(String::from("cpu"), bot::Difficulty::default()),
+		];
+		RoomCommand::StartGame(0).execute(|_|{}, (&mut room, &mut state));
+		
+		let mut playback = Playback::new(room.replay.clone().unwrap());
+		for command in playback.start() {
+			command.execute(|_|{}, (&mut room, &mut state));
+		}
+		assert!(room.players.iter().all(|player|matches!(player.kind, PlayerKind::Network)));
+		assert!(room.units.iter().all(|unit|matches!(unit.kind, unit::Kind::Network)));
+	}
+}
@@ -0,0 +1,251 @@
+use std::collections::VecDeque;
+
+use crate::{Player, State, command::Command, lockstep::{Checksum, Input, Lockstep, Sync, Timing}, ui::GameModeSelection, unit::Unit};
+use itertools::izip;
+use serde::{Serialize, Deserialize};
+
+use crate::PlayerKind;
+use crate::replay::{self, Replay};
+use crate::simulation::Simulation;
+
+// How many of the last chat messages are kept.
+const CHAT_LEN: usize = 8;
+
+#[derive(Default, Serialize, Deserialize, Clone)]
+pub struct Room {
+	pub selected_game_mode: GameModeSelection,
+	pub selected_rotation_system: crate::rotation::Kind,
+	pub gravity: crate::gravity::Kind,
+	pub well_size: crate::game::WellSize,
+	pub top_out_rules: crate::game::TopOutRules,
+	pub randomizer: crate::game::Randomizer,
+	pub preview: crate::unit::Preview,
+	pub hold: crate::unit::Hold,
+	pub timing: Timing,
+	// Set by the host, so that every machine knows the game is played over
+	// the network, even the ones where all of the players are local.
+	pub online: bool,
+	// In frames, only used when the game is online.
+	pub input_delay: u64,
+	pub players: Vec<Player>,
+	// These get sent in Init too, so that whoever joins in the middle of a
+	// game can watch it.
+	pub units: Vec<Unit>,
+	pub commands: Vec<VecDeque<crate::unit::UnitCommandKind>>,
+	pub lockstep: Lockstep,
+	// The last messages, with who sent them.
+	pub chat: VecDeque<(String, String)>,
+	// The game being recorded, if it was started here from the beginning.
+	#[serde(skip)]
+	pub replay: Option<Replay>,
+	
+	pub just_added_player: bool,
+	pub just_initted: bool,
+	pub just_started: bool,
+	pub just_removed_player: Option<usize>,
+	pub just_chatted: bool,
+}
+
+impl Room {
+	pub fn new() -> Self {
+		Self {
+			commands: std::iter::from_fn(||Some(VecDeque::new())).take(crate::MAX_PLAYERS).collect(),
+			..Room::default()
+		}
+	}
+	// The room as the other machines see it, where every unit is a network one.
+	pub fn snapshot(&self) -> Self {
+		let mut room = self.clone();
+		for unit in &mut room.units {
+			unit.kind = crate::unit::Kind::Network;
+		}
+		room
+	}
+	pub fn reset_flags(&mut self) {
+		self.just_added_player = false;
+		self.just_initted = false;
+		self.just_started = false;
+		self.just_removed_player = None;
+		self.just_chatted = false;
+	}
+	// The rules that the units of a game get played with.
+	pub fn get_settings(&self) -> crate::unit::Settings {
+		crate::unit::Settings {
+			mode: self.selected_game_mode.mode().with_hold(self.hold),
+			rotation_system: self.selected_rotation_system,
+			gravity: self.gravity,
+			well_size: self.well_size,
+			top_out_rules: self.top_out_rules,
+			preview: self.preview,
+		}
+	}
+	// Whether every player from the other machines said they're ready.
+	pub fn is_ready(&self) -> bool {
+		self.players.iter()
+			.filter(|player|matches!(player.kind, PlayerKind::Network))
+			.all(|player|player.ready)
+	}
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub enum RoomCommand {
+	Init(Room),
+	// The seed that the local units' minos are generated from.
+	StartGame(u64),
+	StartGameFromSave(Unit),
+	AddPlayer(Player),
+	RemovePlayer(usize),
+	Input(Input),
+	Checksum(Checksum),
+	Sync(Sync),
+	// The player's connection was lost. In the lobby it's removed, and in a
+	// game its unit loses, so that the other units stay where they are.
+	DisconnectPlayer(usize),
+	SetReady(usize, bool),
+	// Sent by a machine rather than a player, as it can have any number of
+	// players, or none at all, so the sender is the machine's name.
+	Chat {sender: String, text: String},
+}
+impl<'a> Command<'a> for RoomCommand {
+	type Params = (&'a mut Room, &'a mut State);
+	fn execute<F>(
+		self, _append: F,
+		(room, state): Self::Params,
+	) {
+		match self {
+			RoomCommand::Init(init_room) => {
+				*room = init_room;
+				// The host's config can have any size in it.
+				room.well_size = room.well_size.clamp();
+				room.just_initted = true;
+				// Joined in the middle of a game.
+				if !room.units.is_empty() {
+					*state = State::play();
+					if let State::Play {players_lost, players_won, ..} = state {
+						*players_lost = room.units.iter().filter(|unit|matches!(unit.base.state, crate::unit::State::Lose)).count() as u32;
+						*players_won = room.units.iter().filter(|unit|matches!(unit.base.state, crate::unit::State::Win)).count() as u32;
+					}
+				}
+			}
+			RoomCommand::StartGame(seed) => {
+				room.just_started = true;
+				room.units.clear();
+				// Everyone gets ready again for the next game.
+				for player in &mut room.players {
+					player.ready = false;
+				}
+				room.replay = Some(Replay::new(room, seed));
+				for commands in &mut room.commands {
+					commands.clear();
+				}
+				*state = State::play();
+				let players_len = room.players.len();
+				let Simulation {units, commands, ..} = Simulation::new(room, seed);
+				room.units = units;
+				for (room_commands, commands) in izip!(&mut room.commands, commands) {
+					*room_commands = commands;
+				}
+				let input_delay = if room.online {room.input_delay} else {0};
+				room.lockstep = Lockstep::new(players_len, input_delay);
+			}
+			RoomCommand::StartGameFromSave(mut unit) => {
+				room.just_started = true;
+				room.units.clear();
+				*state = State::play();
+				if let crate::unit::Kind::Local {mino_controller, ..} = &mut unit.kind {
+					mino_controller.config_id = 0;
+				}
+				room.units.push(unit);
+				room.lockstep = Lockstep::new(1, 0);
+				// The unit doesn't come from a seed, so it can't be played again.
+				room.replay = None;
+			}
+			RoomCommand::AddPlayer(player) => {
+				room.just_added_player = true;
+				room.players.push(player);
+			}
+			// The index comes from another machine, so one that's out of
+			// bounds gets ignored rather than trusted.
+			RoomCommand::RemovePlayer(index) => {
+				if index < room.players.len() {
+					room.just_removed_player = Some(index);
+					room.players.remove(index);
+				}
+			}
+			RoomCommand::Input(input) => {
+				if let Some(replay) = &mut room.replay {
+					replay.record(replay::Event::Input(input.clone()));
+				}
+				room.lockstep.add_input(input);
+			}
+			RoomCommand::Checksum(checksum) => {
+				room.lockstep.add_checksum(checksum);
+			}
+			RoomCommand::Sync(sync) => {
+				if let Some(replay) = &mut room.replay {
+					replay.record(replay::Event::Sync(sync.clone()));
+				}
+				room.lockstep.sync(sync, &mut room.units, &mut room.commands, &room.timing);
+			}
+			RoomCommand::DisconnectPlayer(index) => {
+				if let State::Play {..} = state {
+					let frame = room.lockstep.disconnect(index);
+					if let (Some(replay), Some(frame)) = (&mut room.replay, frame) {
+						replay.record(replay::Event::Disconnect {frame, unit_id: index});
+					}
+				}else if index < room.players.len() {
+					room.just_removed_player = Some(index);
+					room.players.remove(index);
+				}
+			}
+			RoomCommand::SetReady(index, ready) => {
+				if let Some(player) = room.players.get_mut(index) {
+					player.ready = ready;
+				}
+			}
+			RoomCommand::Chat {sender, text} => {
+				room.just_chatted = true;
+				room.chat.push_back((sender, text));
+				if room.chat.len() > CHAT_LEN {
+					room.chat.pop_front();
+				}
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	
+	#[test]
+	fn players_get_ready_again_for_every_game() {
+		let mut room = Room::new();
+		let mut state = State::Lobby;
+		room.players = vec![Player::default(), Player::default()];
+		assert!(!room.is_ready());
+		
+		for index in 0..room.players.len() {
+			RoomCommand::SetReady(index, true).execute(|_|{}, (&mut room, &mut state));
+		}
+		assert!(room.is_ready());
+		
+		RoomCommand::StartGame(0).execute(|_|{}, (&mut room, &mut state));
+		assert!(!room.is_ready());
+	}
+	
+	#[test]
+	fn players_out_of_bounds_are_not_removed() {
+		let mut room = Room::new();
+		let mut state = State::Lobby;
+		room.players = vec![Player::default()];
+		
+		RoomCommand::RemovePlayer(1).execute(|_|{}, (&mut room, &mut state));
+		RoomCommand::DisconnectPlayer(5).execute(|_|{}, (&mut room, &mut state));
+		assert_eq!(room.players.len(), 1);
+		assert_eq!(room.just_removed_player, None);
+		
+		RoomCommand::DisconnectPlayer(0).execute(|_|{}, (&mut room, &mut state));
+		assert!(room.players.is_empty());
+	}
+}
@@ -0,0 +1,423 @@
+use crate::vec2i;
+use crate::mino::{Mino, Shape};
+use crate::game::{self, Well};
+use crate::ui::EnumSelect;
+use enum_select_derive::EnumSelect;
+use serde::{Serialize,Deserialize};
+
+pub trait RotationSystem {
+	// Block positions for every rotation state of the shape, relative to the
+	// top left corner of its bounding box. The first state is the one the
+	// mino spawns in.
+	fn get_states(&self, shape: Shape) -> &'static [[vec2i; 4]];
+	
+	// Offsets that are tried, in order, when `mino` gets rotated into
+	// `rotated_mino`. The first one which doesn't collide is used.
+	fn get_kicks(&self, mino: &Mino, rotated_mino: &Mino, well: &Well) -> &'static [vec2i];
+	
+	fn spawn_mino(&self, shape: Shape) -> Mino {
+		let mut mino = Mino::new(shape, self.get_states(shape)[0]);
+		let (lo,_) = mino.get_rect();
+		mino.translate(vec2i!(0,-lo.y));
+		mino
+	}
+	
+	fn rotate_mino(&self, mino: &mut Mino, rotation: i32) {
+		let states = self.get_states(mino.shape);
+		mino.rotation += rotation;
+		let state = &states[mino.rotation.rem_euclid(states.len() as i32) as usize];
+		for (block, state_block) in mino.blocks.iter_mut().zip(state.iter()) {
+			*block = mino.pos + *state_block;
+		}
+	}
+}
+
+#[derive(Debug, Default, EnumSelect, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Kind {
+	#[default]
+	Srs,
+	Ars,
+	Nrs,
+}
+
+impl Kind {
+	pub fn from_name(name: &str) -> Option<Self> {
+		match name {
+			"srs" => Some(Kind::Srs),
+			"ars" => Some(Kind::Ars),
+			"nrs" => Some(Kind::Nrs),
+			_ => None,
+		}
+	}
+	pub fn system(&self) -> &'static dyn RotationSystem {
+		match self {
+			Kind::Srs => &Srs,
+			Kind::Ars => &Ars,
+			Kind::Nrs => &Nrs,
+		}
+	}
+}
+
+const NO_KICKS: [vec2i; 1] = [vec2i!(0,0)];
+
+const O_STATES: [[vec2i; 4]; 1] = [
+	[vec2i!(0,0), vec2i!(1,0), vec2i!(0,1), vec2i!(1,1)],
+];
+
+// Super Rotation System, used by the guideline games.
+pub struct Srs;
+
+const SRS_I_STATES: [[vec2i; 4]; 4] = [
+	[vec2i!(0,1), vec2i!(1,1), vec2i!(2,1), vec2i!(3,1)],
+	[vec2i!(2,0), vec2i!(2,1), vec2i!(2,2), vec2i!(2,3)],
+	[vec2i!(0,2), vec2i!(1,2), vec2i!(2,2), vec2i!(3,2)],
+	[vec2i!(1,0), vec2i!(1,1), vec2i!(1,2), vec2i!(1,3)],
+];
+const SRS_T_STATES: [[vec2i; 4]; 4] = [
+	[vec2i!(1,0), vec2i!(0,1), vec2i!(1,1), vec2i!(2,1)],
+	[vec2i!(1,0), vec2i!(1,1), vec2i!(2,1), vec2i!(1,2)],
+	[vec2i!(0,1), vec2i!(1,1), vec2i!(2,1), vec2i!(1,2)],
+	[vec2i!(1,0), vec2i!(0,1), vec2i!(1,1), vec2i!(1,2)],
+];
+const SRS_J_STATES: [[vec2i; 4]; 4] = [
+	[vec2i!(0,0), vec2i!(0,1), vec2i!(1,1), vec2i!(2,1)],
+	[vec2i!(1,0), vec2i!(2,0), vec2i!(1,1), vec2i!(1,2)],
+	[vec2i!(0,1), vec2i!(1,1), vec2i!(2,1), vec2i!(2,2)],
+	[vec2i!(1,0), vec2i!(1,1), vec2i!(0,2), vec2i!(1,2)],
+];
+const SRS_L_STATES: [[vec2i; 4]; 4] = [
+	[vec2i!(2,0), vec2i!(0,1), vec2i!(1,1), vec2i!(2,1)],
+	[vec2i!(1,0), vec2i!(1,1), vec2i!(1,2), vec2i!(2,2)],
+	[vec2i!(0,1), vec2i!(1,1), vec2i!(2,1), vec2i!(0,2)],
+	[vec2i!(0,0), vec2i!(1,0), vec2i!(1,1), vec2i!(1,2)],
+];
+const SRS_S_STATES: [[vec2i; 4]; 4] = [
+	[vec2i!(1,0), vec2i!(2,0), vec2i!(0,1), vec2i!(1,1)],
+	[vec2i!(1,0), vec2i!(1,1), vec2i!(2,1), vec2i!(2,2)],
+	[vec2i!(1,1), vec2i!(2,1), vec2i!(0,2), vec2i!(1,2)],
+	[vec2i!(0,0), vec2i!(0,1), vec2i!(1,1), vec2i!(1,2)],
+];
+const SRS_Z_STATES: [[vec2i; 4]; 4] = [
+	[vec2i!(0,0), vec2i!(1,0), vec2i!(1,1), vec2i!(2,1)],
+	[vec2i!(2,0), vec2i!(1,1), vec2i!(2,1), vec2i!(1,2)],
+	[vec2i!(0,1), vec2i!(1,1), vec2i!(1,2), vec2i!(2,2)],
+	[vec2i!(1,0), vec2i!(0,1), vec2i!(1,1), vec2i!(0,2)],
+];
+
+// The kick offsets are the ones from the guideline, but with the y axis
+// flipped, since in the well y grows downwards. The rows are ordered as the
+// transitions below.
+const SRS_TRANSITIONS: [(i32, i32); 8] = [
+	(0,1), (1,0),
+	(1,2), (2,1),
+	(2,3), (3,2),
+	(3,0), (0,3),
+];
+const SRS_JLSTZ_KICKS: [[vec2i; 5]; 8] = [
+	[vec2i!( 0, 0), vec2i!(-1, 0), vec2i!(-1,-1), vec2i!( 0, 2), vec2i!(-1, 2)],
+	[vec2i!( 0, 0), vec2i!( 1, 0), vec2i!( 1, 1), vec2i!( 0,-2), vec2i!( 1,-2)],
+	[vec2i!( 0, 0), vec2i!( 1, 0), vec2i!( 1, 1), vec2i!( 0,-2), vec2i!( 1,-2)],
+	[vec2i!( 0, 0), vec2i!(-1, 0), vec2i!(-1,-1), vec2i!( 0, 2), vec2i!(-1, 2)],
+	[vec2i!( 0, 0), vec2i!( 1, 0), vec2i!( 1,-1), vec2i!( 0, 2), vec2i!( 1, 2)],
+	[vec2i!( 0, 0), vec2i!(-1, 0), vec2i!(-1, 1), vec2i!( 0,-2), vec2i!(-1,-2)],
+	[vec2i!( 0, 0), vec2i!(-1, 0), vec2i!(-1, 1), vec2i!( 0,-2), vec2i!(-1,-2)],
+	[vec2i!( 0, 0), vec2i!( 1, 0), vec2i!( 1,-1), vec2i!( 0, 2), vec2i!( 1, 2)],
+];
+const SRS_I_KICKS: [[vec2i; 5]; 8] = [
+	[vec2i!( 0, 0), vec2i!(-2, 0), vec2i!( 1, 0), vec2i!(-2, 1), vec2i!( 1,-2)],
+	[vec2i!( 0, 0), vec2i!( 2, 0), vec2i!(-1, 0), vec2i!( 2,-1), vec2i!(-1, 2)],
+	[vec2i!( 0, 0), vec2i!(-1, 0), vec2i!( 2, 0), vec2i!(-1,-2), vec2i!( 2, 1)],
+	[vec2i!( 0, 0), vec2i!( 1, 0), vec2i!(-2, 0), vec2i!( 1, 2), vec2i!(-2,-1)],
+	[vec2i!( 0, 0), vec2i!( 2, 0), vec2i!(-1, 0), vec2i!( 2,-1), vec2i!(-1, 2)],
+	[vec2i!( 0, 0), vec2i!(-2, 0), vec2i!( 1, 0), vec2i!(-2, 1), vec2i!( 1,-2)],
+	[vec2i!( 0, 0), vec2i!( 1, 0), vec2i!(-2, 0), vec2i!( 1, 2), vec2i!(-2,-1)],
+	[vec2i!( 0, 0), vec2i!(-1, 0), vec2i!( 2, 0), vec2i!(-1,-2), vec2i!( 2, 1)],
+];
+
+impl RotationSystem for Srs {
+	fn get_states(&self, shape: Shape) -> &'static [[vec2i; 4]] {
+		match shape {
+			Shape::I => &SRS_I_STATES,
+			Shape::O => &O_STATES,
+			Shape::T => &SRS_T_STATES,
+			Shape::J => &SRS_J_STATES,
+			Shape::L => &SRS_L_STATES,
+			Shape::S => &SRS_S_STATES,
+			Shape::Z => &SRS_Z_STATES,
+		}
+	}
+	fn get_kicks(&self, mino: &Mino, rotated_mino: &Mino, _well: &Well) -> &'static [vec2i] {
+		let transition = (mino.rotation.rem_euclid(4), rotated_mino.rotation.rem_euclid(4));
+		let index = SRS_TRANSITIONS.iter().position(|t|*t == transition);
+		match (mino.shape, index) {
+			(Shape::I, Some(index)) => &SRS_I_KICKS[index],
+			(Shape::O, _) | (_, None) => &NO_KICKS,
+			(_, Some(index)) => &SRS_JLSTZ_KICKS[index],
+		}
+	}
+}
+
+// Arika Rotation System, used by the TGM games. The minos spawn flat side up
+// and sit at the bottom of their bounding box.
+pub struct Ars;
+
+const ARS_I_STATES: [[vec2i; 4]; 2] = [
+	[vec2i!(0,1), vec2i!(1,1), vec2i!(2,1), vec2i!(3,1)],
+	[vec2i!(2,0), vec2i!(2,1), vec2i!(2,2), vec2i!(2,3)],
+];
+const ARS_T_STATES: [[vec2i; 4]; 4] = [
+	[vec2i!(0,1), vec2i!(1,1), vec2i!(2,1), vec2i!(1,2)],
+	[vec2i!(1,0), vec2i!(0,1), vec2i!(1,1), vec2i!(1,2)],
+	[vec2i!(1,1), vec2i!(0,2), vec2i!(1,2), vec2i!(2,2)],
+	[vec2i!(1,0), vec2i!(1,1), vec2i!(2,1), vec2i!(1,2)],
+];
+const ARS_J_STATES: [[vec2i; 4]; 4] = [
+	[vec2i!(0,1), vec2i!(1,1), vec2i!(2,1), vec2i!(2,2)],
+	[vec2i!(1,0), vec2i!(1,1), vec2i!(0,2), vec2i!(1,2)],
+	[vec2i!(0,1), vec2i!(0,2), vec2i!(1,2), vec2i!(2,2)],
+	[vec2i!(1,0), vec2i!(2,0), vec2i!(1,1), vec2i!(1,2)],
+];
+const ARS_L_STATES: [[vec2i; 4]; 4] = [
+	[vec2i!(0,1), vec2i!(1,1), vec2i!(2,1), vec2i!(0,2)],
+	[vec2i!(0,0), vec2i!(1,0), vec2i!(1,1), vec2i!(1,2)],
+	[vec2i!(2,1), vec2i!(0,2), vec2i!(1,2), vec2i!(2,2)],
+	[vec2i!(1,0), vec2i!(1,1), vec2i!(1,2), vec2i!(2,2)],
+];
+const ARS_S_STATES: [[vec2i; 4]; 2] = [
+	[vec2i!(1,1), vec2i!(2,1), vec2i!(0,2), vec2i!(1,2)],
+	[vec2i!(0,0), vec2i!(0,1), vec2i!(1,1), vec2i!(1,2)],
+];
+const ARS_Z_STATES: [[vec2i; 4]; 2] = [
+	[vec2i!(0,1), vec2i!(1,1), vec2i!(1,2), vec2i!(2,2)],
+	[vec2i!(2,0), vec2i!(1,1), vec2i!(2,1), vec2i!(1,2)],
+];
+
+const ARS_KICKS: [vec2i; 3] = [vec2i!(0,0), vec2i!(1,0), vec2i!(-1,0)];
+
+// The L, J and T minos don't get kicked if the first blocked cell (in reading
+// order) of their new rotation state is in the center column.
+fn is_blocked_in_center_column(rotated_mino: &Mino, well: &Well) -> bool {
+	let mut blocks = rotated_mino.blocks;
+	blocks.sort_by_key(|block|(block.y, block.x));
+	blocks.iter()
+		.find(|block|game::check_block_well_collision(block, well))
+		.is_some_and(|block|block.x == rotated_mino.pos.x+1)
+}
+
+impl RotationSystem for Ars {
+	fn get_states(&self, shape: Shape) -> &'static [[vec2i; 4]] {
+		match shape {
+			Shape::I => &ARS_I_STATES,
+			Shape::O => &O_STATES,
+			Shape::T => &ARS_T_STATES,
+			Shape::J => &ARS_J_STATES,
+			Shape::L => &ARS_L_STATES,
+			Shape::S => &ARS_S_STATES,
+			Shape::Z => &ARS_Z_STATES,
+		}
+	}
+	fn get_kicks(&self, mino: &Mino, rotated_mino: &Mino, well: &Well) -> &'static [vec2i] {
+		match mino.shape {
+			Shape::I | Shape::O => &NO_KICKS,
+			Shape::L | Shape::J | Shape::T
+			if is_blocked_in_center_column(rotated_mino, well) => &NO_KICKS,
+			_ => &ARS_KICKS,
+		}
+	}
+}
+
+// Nintendo Rotation System, used by the NES game. There are no kicks at all.
+pub struct Nrs;
+
+const NRS_I_STATES: [[vec2i; 4]; 2] = [
+	[vec2i!(0,2), vec2i!(1,2), vec2i!(2,2), vec2i!(3,2)],
+	[vec2i!(2,0), vec2i!(2,1), vec2i!(2,2), vec2i!(2,3)],
+];
+const NRS_T_STATES: [[vec2i; 4]; 4] = [
+	[vec2i!(0,1), vec2i!(1,1), vec2i!(2,1), vec2i!(1,2)],
+	[vec2i!(1,0), vec2i!(0,1), vec2i!(1,1), vec2i!(1,2)],
+	[vec2i!(1,0), vec2i!(0,1), vec2i!(1,1), vec2i!(2,1)],
+	[vec2i!(1,0), vec2i!(1,1), vec2i!(2,1), vec2i!(1,2)],
+];
+const NRS_J_STATES: [[vec2i; 4]; 4] = [
+	[vec2i!(0,1), vec2i!(1,1), vec2i!(2,1), vec2i!(2,2)],
+	[vec2i!(1,0), vec2i!(1,1), vec2i!(0,2), vec2i!(1,2)],
+	[vec2i!(0,0), vec2i!(0,1), vec2i!(1,1), vec2i!(2,1)],
+	[vec2i!(1,0), vec2i!(2,0), vec2i!(1,1), vec2i!(1,2)],
+];
+const NRS_L_STATES: [[vec2i; 4]; 4] = [
+	[vec2i!(0,1), vec2i!(1,1), vec2i!(2,1), vec2i!(0,2)],
+	[vec2i!(0,0), vec2i!(1,0), vec2i!(1,1), vec2i!(1,2)],
+	[vec2i!(2,0), vec2i!(0,1), vec2i!(1,1), vec2i!(2,1)],
+	[vec2i!(1,0), vec2i!(1,1), vec2i!(1,2), vec2i!(2,2)],
+];
+const NRS_S_STATES: [[vec2i; 4]; 2] = [
+	[vec2i!(1,1), vec2i!(2,1), vec2i!(0,2), vec2i!(1,2)],
+	[vec2i!(1,0), vec2i!(1,1), vec2i!(2,1), vec2i!(2,2)],
+];
+const NRS_Z_STATES: [[vec2i; 4]; 2] = [
+	[vec2i!(0,1), vec2i!(1,1), vec2i!(1,2), vec2i!(2,2)],
+	[vec2i!(2,0), vec2i!(1,1), vec2i!(2,1), vec2i!(1,2)],
+];
+
+impl RotationSystem for Nrs {
+	fn get_states(&self, shape: Shape) -> &'static [[vec2i; 4]] {
+		match shape {
+			Shape::I => &NRS_I_STATES,
+			Shape::O => &O_STATES,
+			Shape::T => &NRS_T_STATES,
+			Shape::J => &NRS_J_STATES,
+			Shape::L => &NRS_L_STATES,
+			Shape::S => &NRS_S_STATES,
+			Shape::Z => &NRS_Z_STATES,
+		}
+	}
+	fn get_kicks(&self, _mino: &Mino, _rotated_mino: &Mino, _well: &Well) -> &'static [vec2i] {
+		&NO_KICKS
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::block;
+	
+	// Rows from the top, with a # for every occupied cell.
+	fn create_well(rows: &[&str]) -> Well {
+		let mut well = Well::filled_with(block::Data::EMPTY, rows[0].len(), rows.len());
+		for (y, row) in rows.iter().enumerate() {
+			for (x, cell) in row.chars().enumerate() {
+				if cell == '#' {well[(x,y)] = block::Data::GRAY}
+			}
+		}
+		well
+	}
+	
+	fn create_mino(rotation_system: &dyn RotationSystem, shape: Shape, rotation: i32, pos: vec2i) -> Mino {
+		let mut mino = Mino::new(shape, rotation_system.get_states(shape)[rotation as usize]);
+		mino.rotation = rotation;
+		mino.translate(pos);
+		mino
+	}
+	
+	fn sorted_blocks(mino: &Mino) -> Vec<vec2i> {
+		let mut blocks = mino.blocks.to_vec();
+		blocks.sort_by_key(|block|(block.y, block.x));
+		blocks
+	}
+	
+	#[test]
+	fn srs_rotates_in_place_when_there_is_room() {
+		let well = create_well(&[".........."; 6]);
+		let mut mino = create_mino(&Srs, Shape::T, 0, vec2i!(3,2));
+		assert!(game::try_rotr_mino(&mut mino, &well, &Srs));
+		assert_eq!(mino.last_kick, Some(vec2i!(0,0)));
+		assert_eq!(sorted_blocks(&mino), vec![vec2i!(4,2), vec2i!(4,3), vec2i!(5,3), vec2i!(4,4)]);
+	}
+	
+	#[test]
+	fn srs_t_spin_triple() {
+		let well = create_well(&[
+			"..........",
+			"..........",
+			"...#......",
+			"..........",
+			"###.######",
+			"##..######",
+			"###.######",
+			"####.#####",
+		]);
+		let mut mino = create_mino(&Srs, Shape::T, 0, vec2i!(1,2));
+		assert!(game::try_rotl_mino(&mut mino, &well, &Srs));
+		assert_eq!(mino.last_kick, Some(vec2i!(1,2)));
+		assert_eq!(sorted_blocks(&mino), vec![vec2i!(3,4), vec2i!(2,5), vec2i!(3,5), vec2i!(3,6)]);
+		assert_eq!(game::check_t_spin(&mino, &well), Some(game::TSpin::Full));
+		
+		let mut well = well;
+		game::add_mino_to_well(&mino, &mut well);
+		for y in 4..=6 {
+			assert!((0..10).all(|x|well[(x,y)] != block::Data::EMPTY));
+		}
+	}
+	
+	#[test]
+	fn srs_kicks_i_off_the_right_wall() {
+		let well = create_well(&[".........."; 6]);
+		let mut mino = create_mino(&Srs, Shape::I, 1, vec2i!(7,0));
+		assert!(game::try_rotr_mino(&mut mino, &well, &Srs));
+		assert_eq!(mino.last_kick, Some(vec2i!(-1,0)));
+		assert_eq!(sorted_blocks(&mino), vec![vec2i!(6,2), vec2i!(7,2), vec2i!(8,2), vec2i!(9,2)]);
+	}
+	
+	#[test]
+	fn srs_kicks_i_off_the_left_wall() {
+		let well = create_well(&[".........."; 6]);
+		let mut mino = create_mino(&Srs, Shape::I, 3, vec2i!(-1,0));
+		assert!(game::try_rotl_mino(&mut mino, &well, &Srs));
+		assert_eq!(mino.last_kick, Some(vec2i!(1,0)));
+		assert_eq!(sorted_blocks(&mino), vec![vec2i!(0,2), vec2i!(1,2), vec2i!(2,2), vec2i!(3,2)]);
+	}
+	
+	#[test]
+	fn srs_fails_when_no_kick_fits() {
+		let well = create_well(&[
+			"#.#.......",
+			"#.#.......",
+			"#.#.......",
+			"#.#.......",
+			"##########",
+		]);
+		let mut mino = create_mino(&Srs, Shape::I, 1, vec2i!(-1,0));
+		let blocks = sorted_blocks(&mino);
+		assert!(!game::try_rotr_mino(&mut mino, &well, &Srs));
+		assert!(!game::try_rotl_mino(&mut mino, &well, &Srs));
+		assert_eq!(sorted_blocks(&mino), blocks);
+	}
+	
+	#[test]
+	fn ars_kicks_t_off_the_left_wall() {
+		let well = create_well(&[".........."; 6]);
+		let mut mino = create_mino(&Ars, Shape::T, 3, vec2i!(-1,0));
+		assert!(game::try_rotr_mino(&mut mino, &well, &Ars));
+		assert_eq!(mino.last_kick, Some(vec2i!(1,0)));
+		assert_eq!(sorted_blocks(&mino), vec![vec2i!(0,1), vec2i!(1,1), vec2i!(2,1), vec2i!(1,2)]);
+	}
+	
+	#[test]
+	fn ars_doesnt_kick_when_the_center_column_is_blocked() {
+		let well = create_well(&[
+			"..........",
+			"..........",
+			"..........",
+			"....#.....",
+			"..........",
+			"..........",
+		]);
+		let mut mino = create_mino(&Ars, Shape::T, 0, vec2i!(3,3));
+		assert!(!game::try_rotr_mino(&mut mino, &well, &Ars));
+		assert_eq!(mino.rotation, 0);
+	}
+	
+	#[test]
+	fn ars_doesnt_kick_i() {
+		let well = create_well(&[".........."; 6]);
+		let mut mino = create_mino(&Ars, Shape::I, 1, vec2i!(7,0));
+		assert!(!game::try_rotr_mino(&mut mino, &well, &Ars));
+		assert!(!game::try_rotl_mino(&mut mino, &well, &Ars));
+		assert_eq!(mino.rotation, 1);
+	}
+	
+	#[test]
+	fn nrs_never_kicks() {
+		let well = create_well(&[".........."; 6]);
+		let mut mino = create_mino(&Nrs, Shape::T, 3, vec2i!(-1,0));
+		assert!(!game::try_rotr_mino(&mut mino, &well, &Nrs));
+		
+		let mut mino = create_mino(&Nrs, Shape::I, 1, vec2i!(7,0));
+		assert!(!game::try_rotr_mino(&mut mino, &well, &Nrs));
+		
+		let mut mino = create_mino(&Nrs, Shape::T, 0, vec2i!(3,2));
+		assert!(game::try_rotr_mino(&mut mino, &well, &Nrs));
+		assert_eq!(mino.last_kick, Some(vec2i!(0,0)));
+	}
+}
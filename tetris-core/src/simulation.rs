@@ -0,0 +1,101 @@
+use std::collections::VecDeque;
+use itertools::izip;
+
+use crate::PlayerKind;
+use crate::bot::Bot;
+use crate::game::MinoRng;
+use crate::lockstep::{self, Timing};
+use crate::mino_controller::MinoController;
+use crate::room::Room;
+use crate::unit::{self, Mode, Unit, UnitCommandKind};
+
+// What happened to a unit on a frame.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event {
+	LinesCleared {unit_id: usize, lines: u32},
+	GarbageSent {unit_id: usize, target_unit_id: usize, lines: usize},
+	Lost {unit_id: usize},
+	Won {unit_id: usize},
+}
+
+// Why a frame couldn't be simulated. Nothing changes when it happens.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+	// Every unit needs a list of commands, even an empty one.
+	InputsMismatch {units: usize, inputs: usize},
+}
+
+// The units of a game, and nothing else. It gets advanced one frame at a time,
+// with the commands that every unit got on that frame, which is what lockstep
+// does too, but without any of the waiting for other machines.
+#[derive(Clone)]
+pub struct Simulation {
+	pub units: Vec<Unit>,
+	pub commands: Vec<VecDeque<UnitCommandKind>>,
+	pub timing: Timing,
+	// The next frame to simulate.
+	pub frame: u64,
+}
+
+impl Simulation {
+	// A new game, for every player in the room, with the room's settings.
+	pub fn new(room: &Room, seed: u64) -> Self {
+		let mut units = Vec::new();
+		let mut commands = Vec::new();
+		let mut configs = (0..crate::MAX_PLAYERS).cycle();
+		let players_len = room.players.len();
+		for (unit_id, player) in izip!(0.., &room.players) {
+			let mut unit = match &player.kind {
+				PlayerKind::Local(_) => Unit::local(room.get_settings(), MinoRng::new(room.randomizer, seed), MinoController::new(configs.next().unwrap(), room.gravity)),
+				PlayerKind::Cpu(difficulty) => Unit::cpu(room.get_settings(), MinoRng::new(room.randomizer, seed), Bot::new(*difficulty)),
+				PlayerKind::Network => Unit::network(room.get_settings(), MinoRng::new(room.randomizer, seed)),
+			};
+			let Unit {base, ..} = &mut unit;
+			
+			if let Mode::Versus {target_unit_id,..} = &mut base.mode {
+				*target_unit_id = (unit_id+1usize).rem_euclid(players_len);
+			}
+			
+			let mut unit_commands = VecDeque::new();
+			for _ in 0..=base.preview.len {
+				unit_commands.push_back(UnitCommandKind::NextMino(base.next_mino()));
+			}
+			
+			units.push(unit);
+			commands.push(unit_commands);
+		}
+		Simulation {units, commands, timing: room.timing, frame: 0}
+	}
+	// Simulates a frame, with a list of commands for every unit.
+	pub fn advance(&mut self, inputs: Vec<Vec<UnitCommandKind>>) -> Result<Vec<Event>, Error> {
+		if inputs.len() != self.units.len() {
+			return Err(Error::InputsMismatch {units: self.units.len(), inputs: inputs.len()});
+		}
+		let lines_cleared: Vec<_> = self.units.iter().map(|unit|unit.base.lines_cleared).collect();
+		lockstep::simulate_frame(&mut self.units, &mut self.commands, inputs, &self.timing);
+		self.frame += 1;
+		
+		let mut events = Vec::new();
+		for (unit_id, unit, lines_cleared) in izip!(0.., &mut self.units, lines_cleared) {
+			let base = &mut unit.base;
+			if base.just_cleared_lines {
+				events.push(Event::LinesCleared {unit_id, lines: base.lines_cleared-lines_cleared});
+			}
+			if let (true, Mode::Versus {target_unit_id, ..}) = (base.just_sent_lines > 0, &base.mode) {
+				events.push(Event::GarbageSent {unit_id, target_unit_id: *target_unit_id, lines: base.just_sent_lines});
+			}
+			if base.just_lost {
+				events.push(Event::Lost {unit_id});
+			}
+			if base.just_won {
+				events.push(Event::Won {unit_id});
+			}
+			base.reset_flags();
+		}
+		Ok(events)
+	}
+	// Whether every unit is done, one way or another.
+	pub fn is_over(&self) -> bool {
+		self.units.iter().all(|unit|matches!(unit.base.state, unit::State::Lose | unit::State::Win))
+	}
+}
\ No newline at end of file
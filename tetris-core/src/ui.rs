@@ -10,8 +10,10 @@ pub trait EnumSelect {
 #[derive(PartialEq,EnumSelect,Clone,Copy)]
 pub enum TitleSelection {
 	Continue,
+	WatchReplay,
 	NewGame,
 	GameMode,	
+	RotationSystem,
 	NetworkMode,
 }
 
@@ -32,6 +34,7 @@ pub enum NetworkStateSelection {
 	Offline,
 	Host,
 	Client,
+	Spectator,
 }
 
 #[derive(Default)]
@@ -99,10 +102,21 @@ pub enum GameModeSelection {
 	Sprint,
 	Versus,
 	GameOfLife,
+	Finesse,
 }
 
 use crate::unit::Mode;
 impl GameModeSelection {
+	pub fn from_name(name: &str) -> Option<Self> {
+		match name {
+			"marathon" => Some(GameModeSelection::Marathon),
+			"sprint" => Some(GameModeSelection::Sprint),
+			"versus" => Some(GameModeSelection::Versus),
+			"game-of-life" => Some(GameModeSelection::GameOfLife),
+			"finesse" => Some(GameModeSelection::Finesse),
+			_ => None,
+		}
+	}
 	pub fn mode(&self) -> Mode {
 		use GameModeSelection::*;
 		match *self {
@@ -110,6 +124,7 @@ impl GameModeSelection {
 			Sprint => Mode::default_sprint(),
 			Versus => Mode::default_versus(),
 			GameOfLife => Mode::default_game_of_life(),
+			Finesse => Mode::default_finesse(),
 		}
 	}
 }
@@ -0,0 +1,881 @@
+use std::collections::VecDeque;
+use itertools::izip;
+use serde::{Serialize,Deserialize};
+use crate::{command::Command, game, mino_controller::MinoController};
+use crate::mino::{Mino, Shape};
+use crate::bot::Bot;
+use crate::finesse;
+use crate::{rotation, gravity};
+use std::time::Duration;
+use std::convert::TryFrom;
+use crate::vec2i;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum State {
+	Play,
+	Animation {countdown: Duration},
+	Lose,
+	Win,
+}
+
+// Game of life animation
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GOLAnimation {
+	pub animate_block: array2d::Array2D<Option<crate::block::Data>>
+}
+impl GOLAnimation {
+	fn new(num_rows: usize, num_columns: usize) -> Self {
+		GOLAnimation {
+			animate_block: array2d::Array2D::filled_with(None, num_rows, num_columns)
+		}
+	}
+}
+
+// Line clear animation
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LCAnimation {
+	pub animate_line: Vec<bool>,
+}
+impl LCAnimation {
+	fn new(num: usize) -> Self {
+		LCAnimation {animate_line: vec![false; num]}
+	}
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Unit {
+	pub base: Base,
+	pub kind: Kind,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Base {
+	pub well: game::Well,
+	pub well_size: game::WellSize,
+	pub state: State,
+	
+	pub gol_animation: Option<GOLAnimation>,
+	pub lc_animation: Option<LCAnimation>,
+	
+	pub lines_cleared: u32,
+	pub score: u32,
+	// Starts at -1, and is increased by every consecutive line clear.
+	pub combo: i32,
+	// Whether the last line clear was a difficult one (tetris or T-spin).
+	pub back_to_back: bool,
+	pub mode: Mode,
+	pub rotation_system: rotation::Kind,
+	pub gravity: gravity::Kind,
+	pub top_out_rules: game::TopOutRules,
+	// The rule that ended the game, if it was lost by topping out.
+	pub top_out_reason: Option<game::TopOut>,
+	
+	pub falling_mino: Option<Mino>,
+	// The next minos, in the order that they will fall in.
+	pub queue: VecDeque<Mino>,
+	pub preview: Preview,
+	pub can_store_mino: bool,
+	// Oldest first, the one that comes out of the hold next.
+	pub stored_minos: VecDeque<Mino>,
+	
+	// Moves and rotations made by the falling mino while on the ground, since
+	// it last reached a new lowest row.
+	pub lock_resets: u32,
+	pub lowest_y: i32,
+	
+	// T-spin done by the last mino that was added to the well.
+	pub last_t_spin: Option<game::TSpin>,
+	
+	// TGM's level, which its gravity goes by. It goes up by one for every
+	// mino, except at the end of every hundred, and by one for every line.
+	pub tgm_level: u32,
+	
+	// Moves and rotations that were pressed for the falling mino.
+	pub moves: u32,
+	// Minos that went through more moves than they had to.
+	pub finesse_faults: u32,
+	
+	pub rng: game::MinoRng,
+	pub garbage_rng: game::GarbageRng,
+	
+	pub just_changed_mino: bool,
+	pub just_cleared_lines: bool,
+	pub just_lost: bool,
+	pub just_won: bool,
+	pub just_changed_level: bool,
+	pub just_changed_score: bool,
+	pub just_reset_lock: bool,
+	// Lines of garbage sent to the target unit, in versus.
+	pub just_sent_lines: usize,
+	pub just_faulted: bool,
+}
+
+// The rules that a unit is played with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Settings {
+	pub mode: Mode,
+	pub rotation_system: rotation::Kind,
+	pub gravity: gravity::Kind,
+	pub well_size: game::WellSize,
+	pub top_out_rules: game::TopOutRules,
+	pub preview: Preview,
+}
+
+impl Default for Settings {
+	fn default() -> Self {
+		Settings {
+			mode: Mode::default_marathon(),
+			rotation_system: rotation::Kind::default(),
+			gravity: gravity::Kind::default(),
+			well_size: game::WellSize::default(),
+			top_out_rules: game::TopOutRules::default(),
+			preview: Preview::default(),
+		}
+	}
+}
+
+impl Base {
+	pub fn new(settings: Settings, rng: game::MinoRng) -> Self {
+		let Settings {mode, rotation_system, gravity, well_size, top_out_rules, preview} = settings;
+		Base {
+			state: State::Play,
+			lines_cleared: 0,
+			score: 0,
+			combo: -1,
+			back_to_back: false,
+			mode,
+			rotation_system,
+			gravity,
+			top_out_rules,
+			top_out_reason: None,
+			can_store_mino: true,
+			stored_minos: VecDeque::with_capacity(2),
+			falling_mino: None,
+			queue: VecDeque::with_capacity(preview.len+1),
+			preview,
+			last_t_spin: None,
+			tgm_level: 0,
+			moves: 0,
+			finesse_faults: 0,
+			lock_resets: 0,
+			lowest_y: 0,
+			well: well_size.new_well(),
+			well_size,
+			garbage_rng: game::GarbageRng::new(rng.get_seed()),
+			rng,
+			
+			gol_animation: None,
+			lc_animation: None,
+			
+			just_changed_mino: false,
+			just_cleared_lines: false,
+			just_lost: false,
+			just_won: false,
+			just_changed_level: false,
+			just_changed_score: false,
+			just_reset_lock: false,
+			just_sent_lines: 0,
+			just_faulted: false,
+		}
+	}
+	pub fn reset_flags(&mut self) {
+		self.just_changed_mino = false;
+		self.just_cleared_lines = false;
+		self.just_lost = false;
+		self.just_won = false;
+		self.just_changed_level = false;
+		self.just_changed_score = false;
+		self.just_reset_lock = false;
+		self.just_sent_lines = 0;
+		self.just_faulted = false;
+	}
+	pub fn set_falling_mino(&mut self, mino: Mino) {
+		if let Some(top_out) = game::check_block_out(&mino, &self.well, &self.top_out_rules) {
+			self.top_out(top_out);
+		}
+		self.just_changed_mino = true;
+		self.lowest_y = mino.pos.y;
+		self.lock_resets = 0;
+		self.moves = 0;
+		self.falling_mino = Some(mino);
+		self.apply_instant_gravity();
+	}
+	// Moves the falling mino down at most g rows, and returns how many rows
+	// it actually fell.
+	pub fn fall(&mut self, mut g: i32) -> u32 {
+		let mut rows = 0;
+		if let Some(falling_mino) = &mut self.falling_mino {
+			while g > 0 && game::try_down_mino(falling_mino, &self.well) {
+				g -= 1;
+				rows += 1;
+			}
+			
+			// Reaching a new lowest row gives the mino its lock resets back.
+			if falling_mino.pos.y > self.lowest_y {
+				self.lowest_y = falling_mino.pos.y;
+				self.lock_resets = 0;
+				self.just_reset_lock = true;
+			}
+		}
+		rows
+	}
+	// With 20G, minos fall to the ground as soon as they appear or move.
+	pub fn apply_instant_gravity(&mut self) {
+		if self.get_fall_duration().is_zero() {
+			// Falling isn't a move of its own, so a rotation right before it
+			// still counts for T-spins.
+			let last_kick = self.falling_mino.as_ref().and_then(|mino|mino.last_kick);
+			self.fall(i32::MAX);
+			if let Some(falling_mino) = &mut self.falling_mino {
+				falling_mino.last_kick = last_kick;
+			}
+		}
+	}
+	pub fn get_fall_duration(&self) -> Duration {
+		match self.gravity {
+			gravity::Kind::Tgm => self.gravity.get_fall_duration(self.tgm_level),
+			_ => self.gravity.get_fall_duration(self.get_level()),
+		}
+	}
+	fn raise_tgm_level(&mut self, levels: u32, stops_at_hundred: bool) {
+		if stops_at_hundred && (self.tgm_level % 100 == 99 || self.tgm_level == 998) {return}
+		self.tgm_level = (self.tgm_level + levels).min(999);
+		if let gravity::Kind::Tgm = self.gravity {self.just_changed_level = true}
+	}
+	// Hash of everything that decides how the unit plays out, which has to
+	// come out the same on every machine. The flags aren't part of it, since
+	// they get reset whenever the frame is drawn.
+	pub fn get_checksum(&self) -> u64 {
+		let bytes = bincode::serialize(&(
+			// Nested, because serde only goes up to tuples of 16.
+			&self.well, &self.state, (&self.falling_mino, self.last_t_spin), &self.queue, &self.stored_minos,
+			&self.mode, self.lines_cleared, self.score, self.combo, self.back_to_back,
+			self.can_store_mino, self.lock_resets, self.lowest_y, self.tgm_level, &self.rng, &self.garbage_rng,
+		)).unwrap();
+		// FNV-1a
+		bytes.iter().fold(0xcbf29ce484222325, |hash, byte|(hash ^ *byte as u64).wrapping_mul(0x100000001b3))
+	}
+	pub fn next_mino(&mut self) -> Mino {
+		self.rotation_system.system().spawn_mino(self.rng.generate())
+	}
+	// Changes whenever the lock delay starts over: for a new mino, a new
+	// lowest row, and a move or rotation on the ground.
+	pub fn get_lock_phase(&self) -> LockPhase {
+		(self.rng.get_draws(), self.falling_mino.as_ref().map(|mino|mino.shape), self.lowest_y, self.lock_resets)
+	}
+	pub fn get_level(&self) -> u32 {
+		if let Mode::Marathon {level,..} = self.mode {level} else {1}
+	}
+	pub fn add_score(&mut self, score: u32) {
+		if score > 0 {
+			self.score += score;
+			self.just_changed_score = true;
+		}
+	}
+	// Must be called after every mino that gets added to the well, before the
+	// level gets updated.
+	pub fn score_line_clear(&mut self, lines: u32, perfect_clear: bool) {
+		let level = self.get_level();
+		let mut score = get_line_clear_score(lines, self.last_t_spin);
+		if lines > 0 {
+			let difficult = lines >= 4 || self.last_t_spin.is_some();
+			let back_to_back = difficult && self.back_to_back;
+			if back_to_back {score += score/2}
+			self.combo += 1;
+			score += 50 * self.combo as u32;
+			if perfect_clear {score += get_perfect_clear_score(lines, back_to_back)}
+			self.back_to_back = difficult;
+		}else {
+			self.combo = -1;
+		}
+		self.add_score(score * level);
+	}
+	pub fn win(&mut self) {
+		if !matches!(self.state, State::Win) {
+			self.state = State::Win;
+			self.just_won = true;
+		}
+	}
+	pub fn lose(&mut self) {
+		if !matches!(self.state, State::Lose) {
+			self.state = State::Lose;
+			self.just_lost = true;
+		}
+	}
+	pub fn top_out(&mut self, top_out: game::TopOut) {
+		if !matches!(self.state, State::Lose) {
+			self.top_out_reason = Some(top_out);
+		}
+		self.lose();
+	}
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub enum Kind {
+	Local {
+		mino_controller: MinoController,
+	},
+	Cpu {
+		bot: Bot,
+	},
+	Network
+}
+
+impl Unit {
+	pub fn local(settings: Settings, rng: game::MinoRng, mino_controller: MinoController) -> Unit {
+		Unit {
+			base: Base::new(settings, rng),
+			kind: Kind::Local {mino_controller},
+		}
+	}
+	pub fn cpu(settings: Settings, rng: game::MinoRng, bot: Bot) -> Unit {
+		Unit {
+			base: Base::new(settings, rng),
+			kind: Kind::Cpu {bot},
+		}
+	}
+	pub fn network(settings: Settings, rng: game::MinoRng) -> Unit {
+		Unit {
+			base: Base::new(settings, rng),
+			kind: Kind::Network,
+		}
+	}
+}
+
+// How many of the next minos are shown, and whether they're hidden anyway,
+// for an extra challenge.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Preview {
+	pub len: usize,
+	pub hidden: bool,
+}
+
+impl Preview {
+	pub const MAX_LEN: usize = 7;
+}
+
+impl Default for Preview {
+	fn default() -> Self {
+		Preview {len: 5, hidden: false}
+	}
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Hold {
+	Off,
+	// Once for every mino that gets added to the well.
+	#[default]
+	Once,
+	Unlimited,
+	// Like Once, but with two slots, that minos go through in order.
+	TwoSlot,
+}
+
+impl Hold {
+	pub fn from_name(name: &str) -> Option<Self> {
+		match name {
+			"off" => Some(Hold::Off),
+			"once" => Some(Hold::Once),
+			"unlimited" => Some(Hold::Unlimited),
+			"two-slot" => Some(Hold::TwoSlot),
+			_ => None,
+		}
+	}
+	pub fn get_slots(&self) -> usize {
+		match self {
+			Hold::Off => 0,
+			Hold::Once | Hold::Unlimited => 1,
+			Hold::TwoSlot => 2,
+		}
+	}
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Mode {
+	Marathon {level: u32, level_target: u32, lines_before_next_level: i32, hold: Hold},
+	Sprint {lines_cleared_target: u32, hold: Hold},
+	Versus {lines_received: VecDeque<usize>, lines_received_sum: usize, target_unit_id: usize, hold: Hold},
+	GameOfLife {count: u32, lines_cleared_target: u32, hold: Hold},
+	// Goes on until a mino takes more moves than it has to.
+	Finesse {hold: Hold},
+}
+
+impl Mode {
+	pub fn default_marathon() -> Mode {
+		Mode::Marathon {
+			level_target: 50, level: 1,
+			lines_before_next_level: get_lines_before_next_level(1),
+			hold: Hold::default(),
+		}
+	}
+	pub fn default_sprint() -> Mode {
+		Mode::Sprint {
+			lines_cleared_target: 40,
+			hold: Hold::default(),
+		}
+	}
+	pub fn default_versus() -> Mode {
+		Mode::Versus {
+			lines_received: VecDeque::new(),
+			lines_received_sum: 0,
+			target_unit_id: 0,
+			hold: Hold::default(),
+		}
+	}
+	pub fn default_game_of_life() -> Mode {
+		Mode::GameOfLife {
+			count: 0,
+			lines_cleared_target: 20,
+			hold: Hold::default(),
+		}
+	}
+	pub fn default_finesse() -> Mode {
+		Mode::Finesse {
+			hold: Hold::default(),
+		}
+	}
+	pub fn get_hold(&self) -> Hold {
+		match self {
+			Mode::Marathon {hold,..} | Mode::Sprint {hold,..} |
+			Mode::Versus {hold,..} | Mode::GameOfLife {hold,..} |
+			Mode::Finesse {hold} => *hold,
+		}
+	}
+	pub fn with_hold(mut self, new_hold: Hold) -> Mode {
+		match &mut self {
+			Mode::Marathon {hold,..} | Mode::Sprint {hold,..} |
+			Mode::Versus {hold,..} | Mode::GameOfLife {hold,..} |
+			Mode::Finesse {hold} => *hold = new_hold,
+		}
+		self
+	}
+}
+
+pub fn get_lines_before_next_level(level: u32) -> i32 {
+	10 * (level as i32)
+}
+
+pub fn get_line_clear_score(lines: u32, t_spin: Option<game::TSpin>) -> u32 {
+	use game::TSpin::*;
+	match (t_spin, lines) {
+		(None, 0) => 0,
+		(None, 1) => 100,
+		(None, 2) => 300,
+		(None, 3) => 500,
+		(None, _) => 800,
+		(Some(Mini), 0) => 100,
+		(Some(Mini), 1) => 200,
+		(Some(Mini), _) => 400,
+		(Some(Full), 0) => 400,
+		(Some(Full), 1) => 800,
+		(Some(Full), 2) => 1200,
+		(Some(Full), _) => 1600,
+	}
+}
+
+pub fn get_perfect_clear_score(lines: u32, back_to_back: bool) -> u32 {
+	match lines {
+		1 => 800,
+		2 => 1200,
+		3 => 1800,
+		_ => if back_to_back {3200} else {2000},
+	}
+}
+
+pub fn update_level(
+	level: &mut u32,
+	lines_before_next_level: &mut i32,
+	clearable_lines: u32
+) -> bool {
+	*lines_before_next_level -= clearable_lines as i32;
+	let mut level_changed = false;
+	while *lines_before_next_level <= 0 {
+		*level += 1;
+		*lines_before_next_level +=
+			get_lines_before_next_level(*level);
+		level_changed = true;
+	}
+	level_changed
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub enum UnitEvent {
+	TranslateMino {
+		pos: vec2i,
+		blocks: [vec2i; 4],
+	},
+	AddMinoToWell,
+	GenerateMino {
+		mino: Mino,
+	},
+	AddBottomLines {
+		lines: usize,
+		gap: usize,
+	},
+	StoreMino,
+	Init,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub enum UnitCommandKind {
+	MoveLeft, MoveRight,
+	// Moves from holding left or right down, past the first one.
+	RepeatLeft, RepeatRight,
+	RotateLeft, RotateRight,
+	ApplyGravity(i32), SoftDrop(i32), HardDrop, Lock,
+	Store,
+	AnimClearLines, ClearLines,
+	AnimGameOfLife, GameOfLife,
+	NextMino(Mino),
+	SendLines(usize), AddLines(usize,usize),
+}
+
+pub type UnitCommandInner = (usize, UnitCommandKind);
+pub type LockPhase = (u64, Option<Shape>, i32, u32);
+
+impl<'a> Command<'a> for UnitCommandInner {
+	type Params = &'a mut Unit;
+	fn execute<F>(
+		self, mut append: F,
+		unit: Self::Params,
+	) where F: FnMut(Self) {
+		use UnitCommandKind::*;
+		let (unit_id, kind) = self;
+		let base = &mut unit.base;
+		let mut append = |unit_id, command|append((unit_id, command));
+		match kind {
+			MoveLeft | MoveRight | RepeatLeft | RepeatRight | RotateLeft | RotateRight => {
+				if let Some(falling_mino) = &mut base.falling_mino {
+					let grounded = !game::may_down_mino(falling_mino, &base.well);
+					let rotation_system = base.rotation_system.system();
+					let moved = match kind {
+						MoveLeft | RepeatLeft => game::try_left_mino(falling_mino, &base.well),
+						MoveRight | RepeatRight => game::try_right_mino(falling_mino, &base.well),
+						RotateLeft => game::try_rotl_mino(falling_mino, &base.well, rotation_system),
+						RotateRight => game::try_rotr_mino(falling_mino, &base.well, rotation_system),
+						_ => unreachable!(),
+					};
+					// Every press counts, even one into a wall.
+					if !matches!(kind, RepeatLeft | RepeatRight) {
+						base.moves += 1;
+					}
+					// Moving a mino that sits on the ground restarts its lock delay.
+					if moved && grounded {
+						base.lock_resets += 1;
+						base.just_reset_lock = true;
+					}
+				}
+				base.apply_instant_gravity();
+			}
+			ApplyGravity(_) | SoftDrop(_) | HardDrop => {
+				// Soft drops are worth 1 point for every row, and hard drops 2.
+				let (g, drop_score) = match kind {
+					SoftDrop(g) => (g, 1),
+					HardDrop => (i32::MAX, 2),
+					ApplyGravity(g) => (g, 0),
+					_ => unreachable!(),
+				};
+				let rows = base.fall(g);
+				base.add_score(rows * drop_score);
+				if let HardDrop = kind {
+					lock_mino(unit_id, unit, &mut append);
+				}
+			}
+			Lock => {
+				// The commands before this one might have moved the mino off
+				// the ground.
+				let grounded = base.falling_mino.as_ref()
+					.is_some_and(|mino|!game::may_down_mino(mino, &base.well));
+				if grounded {
+					lock_mino(unit_id, unit, &mut append);
+				}
+			}
+			AnimClearLines => {
+				let mut clearable_lines = 0;
+				let mut sendable_lines = 0;
+				let mut perfect_clear = true;
+				let mut lc_animation = LCAnimation::new(base.well.row_len());
+				for (row, clearable) in izip!(base.well.columns_iter(), lc_animation.animate_line.iter_mut()) {
+					let mut count = 0;
+					let mut sendable = true;
+					for block in row {
+						count += (!block.is_empty()) as u32;
+						sendable &= *block != crate::block::Data::GRAY;
+					}
+					if count as usize == base.well.column_len() {
+						clearable_lines += 1;
+						sendable_lines += sendable as usize;
+						*clearable = true;
+					}else if count != 0 {
+						perfect_clear = false;
+					}
+				}
+				
+				base.score_line_clear(clearable_lines, clearable_lines > 0 && perfect_clear);
+				
+				if let Mode::Versus {lines_received,..} = &mut base.mode {
+					while let Some(lines) = lines_received.pop_front() {
+						let row = base.garbage_rng.get_gap(base.well.num_rows());
+						append(unit_id, AddLines(lines,row));
+					}
+				}
+				
+				if clearable_lines > 0 {
+					base.just_cleared_lines = true;
+					base.state = State::Animation {countdown: Duration::from_secs(0)};
+					base.lc_animation = Some(lc_animation);
+					base.lines_cleared += clearable_lines;
+					base.raise_tgm_level(clearable_lines, false);
+					match &mut base.mode {
+						Mode::Marathon {level,lines_before_next_level,..} => {
+							let level_changed = update_level(level, lines_before_next_level, clearable_lines);
+							if level_changed {base.just_changed_level = true}
+						}
+						Mode::Versus {target_unit_id,..} => {
+							base.just_sent_lines += sendable_lines;
+							append(*target_unit_id, SendLines(sendable_lines));
+						}
+						_ => {}
+					}
+				}
+			}
+			ClearLines => {
+				base.lc_animation = None;
+				game::try_clear_lines(&mut base.well);
+				match &base.mode {
+					Mode::Marathon {level,level_target,..} =>
+					if *level >= *level_target {base.win()}
+					Mode::Sprint {lines_cleared_target,..} =>
+					if base.lines_cleared >= *lines_cleared_target {base.win()}
+					Mode::GameOfLife {lines_cleared_target,..} =>
+					if base.lines_cleared >= *lines_cleared_target {base.win()}
+					_ => {}
+				}
+			}
+			AnimGameOfLife => {
+				if let Mode::GameOfLife {count, ..} = &mut base.mode {
+					*count += 1;
+					if *count % 5 == 0 { //TODO: change me
+						base.state = State::Animation {countdown: Duration::from_secs(0)};
+						let mut gol_animation = GOLAnimation::new(base.well.column_len(), base.well.row_len());
+						let (dx, dy) = (vec![0, 1, 0, -1], vec![1, 0, -1, 0]);
+						
+						// This animation_block is a trick that I came up with; it makes the
+						// game of life blocks seem random, but it's also deterministic. This
+						// means it doesn't have to be sent over the network :).
+						let mut animation_block = crate::block::Data::BLUE;
+						let mut animation_block_count = 0;
+						for x in 0..base.well.column_len() as i32 {
+							for y in 0..base.well.row_len() as i32 {
+								let mut neighbors = 0;
+								for (dx, dy) in izip!(&dx, &dy) {
+									let (x, y) = (x - *dx, y - *dy);
+									let (x, y) = (usize::try_from(x).ok(), usize::try_from(y).ok());
+									if let (Some(x), Some(y)) = (x, y) {
+										if let Some(block) = base.well.get(x, y) {
+											if !block.is_empty() {
+												neighbors += 1;
+												animation_block_count += 1;
+												if animation_block_count == 3 {
+													animation_block_count = 0;
+													animation_block = *block;
+												}
+											}
+										}
+									}
+								}
+								
+								let (ux, uy) = (x as usize, y as usize);
+								let block = &mut gol_animation.animate_block[(ux, uy)];
+								if !base.well[(ux,uy)].is_empty() {if neighbors != 3 {block.get_or_insert(crate::block::Data::EMPTY);}}
+								else {if neighbors == 3 {block.get_or_insert(animation_block);}}
+							}
+						}
+						base.gol_animation = Some(gol_animation);
+					}
+				}
+			}
+			GameOfLife => {
+				if let Some(mut gol_animation) = base.gol_animation.take() {
+					for x in 0..base.well.column_len() {
+						for y in 0..base.well.row_len() {
+							let p = (x,y);
+							if let Some(block) = gol_animation.animate_block[p].take() {
+								base.well[p] = block;
+							}
+						}
+					}
+				}
+			}
+			Store => {
+				let hold = base.mode.get_hold();
+				let can_store_mino = match hold {
+					Hold::Off => false,
+					Hold::Unlimited => true,
+					Hold::Once | Hold::TwoSlot => base.can_store_mino,
+				};
+				if can_store_mino {
+					base.can_store_mino = false;
+					if let Some(mut falling_mino) = base.falling_mino.take() {
+						game::reset_mino(&mut falling_mino, base.rotation_system.system());
+						base.stored_minos.push_back(falling_mino);
+						if base.stored_minos.len() > hold.get_slots() {
+							let mut stored_mino = base.stored_minos.pop_front().unwrap();
+							game::center_mino(&mut stored_mino, &base.well_size);
+							base.set_falling_mino(stored_mino);
+						}else {
+							append(unit_id, NextMino(base.next_mino()));
+						}
+					}
+				}
+			}
+			NextMino(mino) => {
+				// The queue first gets filled up to the preview length, and
+				// only then do minos start coming out of it.
+				base.queue.push_back(mino);
+				if base.queue.len() > base.preview.len {
+					let mut mino = base.queue.pop_front().unwrap();
+					game::center_mino(&mut mino, &base.well_size);
+					base.set_falling_mino(mino);
+				}
+			}
+			SendLines(lines) => {
+				if let Mode::Versus {lines_received, lines_received_sum, ..} = &mut base.mode {
+					lines_received.push_back(lines);
+					*lines_received_sum += lines;
+				}
+			}
+			AddLines(lines, gap) => {
+				if let Mode::Versus {lines_received_sum, ..} = &mut base.mode {
+					*lines_received_sum -= lines;
+				}
+				if lines == 0 {return}
+				for y in 0..base.well.row_len() {
+					for x in 0..base.well.column_len() {
+						if !base.well[(x,y)].is_empty() {
+							if y >= lines {
+								base.well[(x,y-lines)] = base.well[(x,y)];
+							}
+						}
+						base.well[(x,y)] = if y >= base.well.row_len()-lines && x != gap
+						{crate::block::Data::GRAY} else {crate::block::Data::EMPTY}
+					}
+				}
+			}
+		}
+	}
+}
+// Adds the falling mino to the well, and queues up everything that follows
+// from that.
+fn lock_mino<F>(unit_id: usize, unit: &mut Unit, append: &mut F)
+where F: FnMut(usize, UnitCommandKind) {
+	use UnitCommandKind::*;
+	let base = &mut unit.base;
+	if let Some(falling_mino) = &base.falling_mino {
+		let top_out = game::check_lock_out(falling_mino, &base.well, &base.well_size, &base.top_out_rules);
+		if let Some(top_out) = top_out {
+			base.top_out(top_out);
+		}else {
+			base.can_store_mino = true;
+			base.last_t_spin = game::check_t_spin(falling_mino, &base.well);
+			let min_moves = finesse::get_min_moves(falling_mino, &base.well, &base.well_size, base.rotation_system.system());
+			game::force_mino_into_well(falling_mino, &mut base.well);
+			base.raise_tgm_level(1, true);
+			
+			append(unit_id, NextMino(base.next_mino()));
+			append(unit_id, AnimClearLines);
+			append(unit_id, ClearLines);
+			if let Mode::GameOfLife {..} = base.mode {
+				append(unit_id, AnimGameOfLife);
+				append(unit_id, GameOfLife);
+			}
+			
+			if min_moves.is_some_and(|min_moves|base.moves > min_moves) {
+				base.finesse_faults += 1;
+				base.just_faulted = true;
+				if let Mode::Finesse {..} = base.mode {
+					base.lose();
+				}
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{block, mino::Shape};
+	
+	fn create_base(gravity: gravity::Kind) -> Base {
+		Base::new(Settings {gravity, ..Settings::default()}, game::MinoRng::new(game::Randomizer::default(), 0))
+	}
+	
+	#[test]
+	fn instant_gravity_keeps_the_rotation_for_t_spins() {
+		let mut base = create_base(gravity::Kind::Instant);
+		for (x, y) in [(3,19), (0,20), (1,20), (2,20), (3,20), (6,20), (7,20), (8,20), (9,20)] {
+			base.well[(x,y)] = block::Data::GRAY;
+		}
+		for x in (0..10).filter(|x|*x != 4) {
+			base.well[(x,21)] = block::Data::GRAY;
+		}
+		
+		let mut mino = base.rotation_system.system().spawn_mino(Shape::T);
+		mino.translate(vec2i!(3,17));
+		base.set_falling_mino(mino);
+		assert_eq!(base.falling_mino.as_ref().unwrap().pos, vec2i!(3,17));
+		
+		let rotation_system = base.rotation_system.system();
+		assert!(game::try_rotr_mino(base.falling_mino.as_mut().unwrap(), &base.well, rotation_system));
+		base.apply_instant_gravity();
+		
+		let mino = base.falling_mino.as_ref().unwrap();
+		assert_eq!(mino.pos, vec2i!(3,19));
+		assert_eq!(mino.last_kick, Some(vec2i!(0,0)));
+		assert_eq!(game::check_t_spin(mino, &base.well), Some(game::TSpin::Mini));
+	}
+	
+	#[test]
+	fn tgm_level_stops_at_the_end_of_every_hundred() {
+		let mut base = create_base(gravity::Kind::Tgm);
+		for _ in 0..150 {base.raise_tgm_level(1, true)}
+		assert_eq!(base.tgm_level, 99);
+		assert!(base.just_changed_level);
+		
+		base.raise_tgm_level(2, false);
+		base.raise_tgm_level(1, true);
+		assert_eq!(base.tgm_level, 102);
+		
+		for _ in 0..1000 {base.raise_tgm_level(4, false)}
+		assert_eq!(base.tgm_level, 999);
+	}
+	
+	#[test]
+	fn moves_count_presses_but_not_repeats() {
+		let settings = Settings {mode: Mode::default_finesse(), ..Settings::default()};
+		let mut unit = Unit::network(settings, game::MinoRng::new(game::Randomizer::default(), 0));
+		let mut mino = unit.base.rotation_system.system().spawn_mino(Shape::O);
+		game::center_mino(&mut mino, &unit.base.well_size);
+		unit.base.set_falling_mino(mino);
+		
+		let commands = [
+			UnitCommandKind::MoveLeft, UnitCommandKind::RepeatLeft, UnitCommandKind::RepeatLeft,
+			UnitCommandKind::RepeatLeft, UnitCommandKind::RepeatLeft, UnitCommandKind::MoveLeft,
+		];
+		for command in commands {
+			(0, command).execute(|_|{}, &mut unit);
+		}
+		// The last press went into the wall.
+		assert_eq!(unit.base.falling_mino.as_ref().unwrap().blocks.iter().map(|block|block.x).min(), Some(0));
+		assert_eq!(unit.base.moves, 2);
+	}
+	
+	#[test]
+	fn checksum_covers_the_last_t_spin() {
+		let mut base = create_base(gravity::Kind::Classic);
+		let checksum = base.get_checksum();
+		assert_eq!(create_base(gravity::Kind::Classic).get_checksum(), checksum);
+		base.last_t_spin = Some(game::TSpin::Mini);
+		assert_ne!(base.get_checksum(), checksum);
+	}
+}
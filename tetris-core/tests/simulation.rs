@@ -0,0 +1,42 @@
+use tetris_core::{Player, mino::Shape, room::Room, game::WellSize, ui::GameModeSelection};
+use tetris_core::simulation::{Error, Event, Simulation};
+use tetris_core::unit::UnitCommandKind;
+
+// A well that a single I piece fills a row of, and a queue of nothing but I
+// pieces.
+fn create_simulation(i_pieces: usize) -> Simulation {
+	let mut room = Room::new();
+	room.selected_game_mode = GameModeSelection::Marathon;
+	room.well_size = WellSize {width: 4, ..WellSize::default()};
+	room.preview.len = i_pieces - 1;
+	room.players = vec![Player::default()];
+	
+	let mut simulation = Simulation::new(&room, 0);
+	let i_piece = room.selected_rotation_system.system().spawn_mino(Shape::I);
+	simulation.commands[0] = (0..i_pieces).map(|_|UnitCommandKind::NextMino(i_piece.clone())).collect();
+	simulation
+}
+
+#[test]
+fn four_i_pieces_clear_four_lines() {
+	let mut simulation = create_simulation(4);
+	let mut events = Vec::new();
+	for _ in 0..4 {
+		events.extend(simulation.advance(vec![vec![UnitCommandKind::HardDrop]]).unwrap());
+		// Time for the line clear animation.
+		for _ in 0..2 {
+			events.extend(simulation.advance(vec![Vec::new()]).unwrap());
+		}
+	}
+	
+	assert_eq!(events, vec![Event::LinesCleared {unit_id: 0, lines: 1}; 4]);
+	assert_eq!(simulation.units[0].base.lines_cleared, 4);
+	assert!(!simulation.is_over());
+}
+
+#[test]
+fn every_unit_needs_inputs() {
+	let mut simulation = create_simulation(4);
+	assert_eq!(simulation.advance(Vec::new()), Err(Error::InputsMismatch {units: 1, inputs: 0}));
+	assert_eq!(simulation.frame, 0);
+}